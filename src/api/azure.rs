@@ -0,0 +1,79 @@
+use crate::api::openai::{chat_completion, chat_completion_candidates};
+use crate::api::{Provider, Request};
+use crate::config::{AzureConfig, Platform};
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+
+// Azure OpenAI Serviceのデプロイメント
+pub struct AzureProvider {
+    settings: AzureConfig,
+}
+
+impl AzureProvider {
+    pub fn new(settings: AzureConfig) -> Self {
+        Self { settings }
+    }
+
+    fn endpoint(&self) -> Result<String> {
+        let settings = &self.settings;
+        if settings.resource.is_empty() || settings.deployment.is_empty() {
+            return Err(anyhow!(
+                "Azure OpenAI deployment is not configured. Please set it with 'aic config --api'"
+            ));
+        }
+
+        Ok(format!(
+            "{}/openai/deployments/{}/chat/completions?api-version={}",
+            settings.endpoint(),
+            settings.deployment,
+            settings.api_version
+        ))
+    }
+}
+
+#[async_trait]
+impl Provider for AzureProvider {
+    fn platform(&self) -> Platform {
+        Platform::Azure
+    }
+
+    fn name(&self) -> &'static str {
+        "Azure OpenAI"
+    }
+
+    fn label(&self) -> &'static str {
+        "Azure OpenAI (deployment)"
+    }
+
+    fn env_var_name(&self) -> &'static str {
+        "AZURE_OPENAI_API_KEY"
+    }
+
+    fn default_model_name(&self) -> &'static str {
+        ""
+    }
+
+    fn token_budget(&self) -> usize {
+        60_000
+    }
+
+    fn models(&self) -> Vec<(&'static str, &'static str)> {
+        vec![]
+    }
+
+    async fn generate(&self, request: &Request<'_>) -> Result<String> {
+        let client = request.client;
+        let url = self.endpoint()?;
+
+        let builder = client.post(url).header("api-key", request.api_key);
+
+        chat_completion(builder, request, "Azure OpenAI").await
+    }
+
+    async fn generate_candidates(&self, request: &Request<'_>) -> Result<Vec<String>> {
+        let url = self.endpoint()?;
+        let builder = request.client.post(url).header("api-key", request.api_key);
+
+        chat_completion_candidates(builder, request, "Azure OpenAI").await
+    }
+}
@@ -1,6 +1,10 @@
 use crate::api::ANTHROPIC_API_BASE_ENV;
-use anyhow::{Result, anyhow};
-use reqwest::Client;
+use crate::api::retry::send_with_retry;
+use crate::api::stream::read_events;
+use crate::api::{ApiError, Provider, Request, error_from_response};
+use crate::config::Platform;
+use anyhow::Result;
+use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use std::env;
 
@@ -10,6 +14,7 @@ pub struct ClaudeRequest {
     max_tokens: u32,
     system: String,
     messages: Vec<Message>,
+    stream: bool,
 }
 
 #[derive(Serialize)]
@@ -37,51 +42,150 @@ pub struct ResponseContent {
     text: String,
 }
 
-pub async fn call_api(
-    api_key: &str,
-    model: &str,
-    system_prompt: &str,
-    user_prompt: &str,
-) -> Result<String> {
-    let client = Client::new();
-
-    // ベースURLを環境変数から取得（テスト用）
-    let base_url = env::var(ANTHROPIC_API_BASE_ENV)
-        .unwrap_or_else(|_| "https://api.anthropic.com".to_string());
-    let url = format!("{}/v1/messages", base_url);
-
-    let request = ClaudeRequest {
-        model: model.to_string(),
-        max_tokens: 1000,
-        system: system_prompt.to_string(),
-        messages: vec![Message {
-            role: "user".to_string(),
-            content: vec![Content {
-                content_type: "text".to_string(),
-                text: user_prompt.to_string(),
-            }],
-        }],
-    };
-
-    let response = client
-        .post(url)
-        .header("x-api-key", api_key)
-        .header("anthropic-version", "2023-06-01")
-        .header("content-type", "application/json")
-        .json(&request)
-        .send()
-        .await?;
-
-    if !response.status().is_success() {
-        let error_text = response.text().await?;
-        return Err(anyhow!("Claude API error: {}", error_text));
+// ストリーミング時に受け取るイベント
+#[derive(Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StreamEvent {
+    ContentBlockDelta {
+        delta: StreamDelta,
+    },
+    Error {
+        error: serde_json::Value,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct StreamDelta {
+    #[serde(default)]
+    text: String,
+}
+
+pub struct ClaudeProvider {
+    base_url: Option<String>,
+}
+
+impl ClaudeProvider {
+    pub const DEFAULT: Self = Self { base_url: None };
+
+    // 接続先を明示的に指定する（テスト用）
+    #[cfg(test)]
+    pub fn with_base_url(base_url: impl Into<String>) -> Self {
+        Self {
+            base_url: Some(base_url.into()),
+        }
     }
 
-    let claude_response: ClaudeResponse = response.json().await?;
+    fn base_url(&self) -> String {
+        // ベースURLを環境変数から取得（テスト用）
+        self.base_url.clone().unwrap_or_else(|| {
+            env::var(ANTHROPIC_API_BASE_ENV)
+                .unwrap_or_else(|_| "https://api.anthropic.com".to_string())
+        })
+    }
+}
+
+#[async_trait]
+impl Provider for ClaudeProvider {
+    fn platform(&self) -> Platform {
+        Platform::Claude
+    }
+
+    fn name(&self) -> &'static str {
+        "Claude"
+    }
+
+    fn label(&self) -> &'static str {
+        "Claude (Anthropic)"
+    }
 
-    if claude_response.content.is_empty() || claude_response.content[0].content_type != "text" {
-        return Err(anyhow!("Unexpected response format from Claude API"));
+    fn env_var_name(&self) -> &'static str {
+        "CLAUDE_API_KEY"
     }
 
-    Ok(claude_response.content[0].text.clone())
+    fn default_model_name(&self) -> &'static str {
+        "claude-3-opus-20240229"
+    }
+
+    fn chars_per_token(&self) -> f64 {
+        3.5
+    }
+
+    // 200Kトークンのコンテキストのうち、応答とプロンプトの分を残す
+    fn token_budget(&self) -> usize {
+        100_000
+    }
+
+    fn models(&self) -> Vec<(&'static str, &'static str)> {
+        vec![
+            ("Claude 3.7 Sonnet", "claude-3-7-sonnet-20250219"),
+            ("Claude 3.5 Sonnet	", "claude-3-5-sonnet-20240620"),
+            ("Claude 3.5 Haiku", "claude-3-5-haiku-20241022"),
+        ]
+    }
+
+    async fn generate(&self, request: &Request<'_>) -> Result<String> {
+        let client = request.client;
+
+        let base_url = self.base_url();
+        let url = format!("{}/v1/messages", base_url);
+
+        let body = ClaudeRequest {
+            model: request.model.to_string(),
+            max_tokens: 1000,
+            system: request.system_prompt.to_string(),
+            messages: vec![Message {
+                role: "user".to_string(),
+                content: vec![Content {
+                    content_type: "text".to_string(),
+                    text: request.user_prompt.to_string(),
+                }],
+            }],
+            stream: request.on_token.is_some(),
+        };
+
+        let builder = client
+            .post(url)
+            .header("x-api-key", request.api_key)
+            .header("anthropic-version", "2023-06-01")
+            .header("content-type", "application/json")
+            .json(&body);
+        let response = send_with_retry(builder, &request.retry, "Claude").await?;
+
+        if !response.status().is_success() {
+            return Err(error_from_response(response, "Claude").await);
+        }
+
+        if let Some(on_token) = request.on_token {
+            let mut message = String::new();
+            read_events(response, |data| {
+                match serde_json::from_str::<StreamEvent>(data)? {
+                    StreamEvent::ContentBlockDelta { delta } => {
+                        on_token(&delta.text);
+                        message.push_str(&delta.text);
+                    }
+                    StreamEvent::Error { error } => {
+                        return Err(ApiError::from_event("Claude", &error).into());
+                    }
+                    StreamEvent::Other => {}
+                }
+                Ok(())
+            })
+            .await?;
+            return Ok(message);
+        }
+
+        let claude_response: ClaudeResponse = response.json().await?;
+
+        if claude_response.content.is_empty() || claude_response.content[0].content_type != "text" {
+            return Err(ApiError::malformed(
+                "Claude",
+                "Unexpected response format from Claude API",
+            )
+            .into());
+        }
+
+        Ok(claude_response.content[0].text.clone())
+    }
 }
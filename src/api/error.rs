@@ -0,0 +1,272 @@
+use reqwest::StatusCode;
+use serde_json::Value;
+use std::fmt;
+
+// APIエラーの分類
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApiErrorKind {
+    Auth,
+    RateLimited,
+    Overloaded,
+    ContextTooLong,
+    InvalidModel,
+    Network,
+    MalformedResponse,
+    ContentFiltered,
+    Other,
+}
+
+impl ApiErrorKind {
+    // ユーザーが取るべき対応
+    pub fn hint(&self) -> Option<&'static str> {
+        match self {
+            ApiErrorKind::Auth => {
+                Some("Check your API key, or run `aic config --api` to set it again.")
+            }
+            ApiErrorKind::RateLimited => Some(
+                "You are being rate limited. Wait a moment and try again, or configure `fallbacks` to use another provider.",
+            ),
+            ApiErrorKind::Overloaded => Some(
+                "The service is temporarily unavailable. Try again later, or configure `fallbacks` to use another provider.",
+            ),
+            ApiErrorKind::ContextTooLong => Some(
+                "The staged diff is too large for the model. Stage fewer files, or choose a model with a larger context window.",
+            ),
+            ApiErrorKind::InvalidModel => {
+                Some("The model is not available. Choose another one with `aic config --api`.")
+            }
+            ApiErrorKind::Network => Some(
+                "Could not reach the API. Check your network connection, or increase the timeout with `--timeout`.",
+            ),
+            ApiErrorKind::MalformedResponse => {
+                Some("The API returned an unexpected response. Try again or choose another model.")
+            }
+            ApiErrorKind::ContentFiltered => {
+                Some("The provider's content filter blocked the request or the response.")
+            }
+            ApiErrorKind::Other => None,
+        }
+    }
+
+    // 種類ごとに異なる終了コード
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            ApiErrorKind::Auth => 3,
+            ApiErrorKind::RateLimited => 4,
+            ApiErrorKind::Overloaded => 5,
+            ApiErrorKind::ContextTooLong => 6,
+            ApiErrorKind::InvalidModel => 7,
+            ApiErrorKind::Network => 8,
+            ApiErrorKind::MalformedResponse => 9,
+            ApiErrorKind::ContentFiltered => 10,
+            ApiErrorKind::Other => 1,
+        }
+    }
+}
+
+// プロバイダーが返したエラー
+#[derive(Debug)]
+pub struct ApiError {
+    pub kind: ApiErrorKind,
+    pub api_name: String,
+    pub status: Option<StatusCode>,
+    pub message: String,
+}
+
+impl ApiError {
+    pub fn new(kind: ApiErrorKind, api_name: &str, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            api_name: api_name.to_string(),
+            status: None,
+            message: message.into(),
+        }
+    }
+
+    pub fn malformed(api_name: &str, message: impl Into<String>) -> Self {
+        Self::new(ApiErrorKind::MalformedResponse, api_name, message)
+    }
+
+    // エラーレスポンスのステータスと本文から分類する
+    pub fn from_response(api_name: &str, status: StatusCode, body: &str) -> Self {
+        let details = ErrorDetails::parse(body);
+        let kind = classify(Some(status), &details);
+
+        Self {
+            kind,
+            api_name: api_name.to_string(),
+            status: Some(status),
+            message: details.message,
+        }
+    }
+
+    // ストリーミング中に受け取ったエラーイベントから分類する
+    pub fn from_event(api_name: &str, error: &Value) -> Self {
+        let details = ErrorDetails::from_value(error, error.to_string());
+        let kind = classify(None, &details);
+
+        Self::new(kind, api_name, details.message)
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.status {
+            Some(status) => write!(
+                f,
+                "{} API error ({}): {}",
+                self.api_name, status, self.message
+            ),
+            None => write!(f, "{} API error: {}", self.api_name, self.message),
+        }
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+// 各プロバイダーのエラーJSONから取り出した情報
+//   Claude: {"type":"error","error":{"type":"...","message":"..."}}
+//   OpenAI: {"error":{"message":"...","type":"...","code":"..."}}
+//   Gemini: {"error":{"code":400,"message":"...","status":"..."}}
+//   Ollama: {"error":"..."}
+struct ErrorDetails {
+    message: String,
+    // type, code, status など種類を表す文字列
+    codes: Vec<String>,
+}
+
+impl ErrorDetails {
+    fn parse(body: &str) -> Self {
+        match serde_json::from_str::<Value>(body) {
+            Ok(value) => Self::from_value(&value, body.to_string()),
+            Err(_) => Self {
+                message: body.trim().to_string(),
+                codes: Vec::new(),
+            },
+        }
+    }
+
+    fn from_value(value: &Value, fallback: String) -> Self {
+        let error = value.get("error").unwrap_or(value);
+
+        if let Some(message) = error.as_str() {
+            return Self {
+                message: message.to_string(),
+                codes: Vec::new(),
+            };
+        }
+
+        let codes: Vec<String> = ["type", "code", "status"]
+            .iter()
+            .filter_map(|key| error.get(*key))
+            .filter_map(|v| v.as_str().map(str::to_string))
+            .collect();
+
+        let message = error
+            .get("message")
+            .and_then(Value::as_str)
+            .map(str::to_string)
+            .or_else(|| codes.first().cloned())
+            .unwrap_or(fallback);
+
+        Self { message, codes }
+    }
+
+    fn has_code(&self, candidates: &[&str]) -> bool {
+        self.codes
+            .iter()
+            .any(|code| candidates.contains(&code.as_str()))
+    }
+
+    fn message_contains(&self, needles: &[&str]) -> bool {
+        let message = self.message.to_lowercase();
+        needles.iter().any(|needle| message.contains(needle))
+    }
+}
+
+fn classify(status: Option<StatusCode>, details: &ErrorDetails) -> ApiErrorKind {
+    let status = status.map(|s| s.as_u16());
+
+    if details.has_code(&["content_filter", "SAFETY"])
+        || details.message_contains(&["content management policy", "content filter"])
+    {
+        return ApiErrorKind::ContentFiltered;
+    }
+
+    if details.has_code(&["context_length_exceeded", "request_too_large"])
+        || status == Some(413)
+        || details.message_contains(&[
+            "prompt is too long",
+            "maximum context length",
+            "context length",
+            "too many tokens",
+            "exceeds the maximum number of tokens",
+        ])
+    {
+        return ApiErrorKind::ContextTooLong;
+    }
+
+    if matches!(status, Some(401 | 403))
+        || details.has_code(&[
+            "authentication_error",
+            "permission_error",
+            "invalid_api_key",
+            "UNAUTHENTICATED",
+            "PERMISSION_DENIED",
+        ])
+        || details.message_contains(&["api key not valid", "invalid api key"])
+    {
+        return ApiErrorKind::Auth;
+    }
+
+    if status == Some(429)
+        || details.has_code(&[
+            "rate_limit_error",
+            "rate_limit_exceeded",
+            "RESOURCE_EXHAUSTED",
+        ])
+    {
+        return ApiErrorKind::RateLimited;
+    }
+
+    if details.has_code(&["model_not_found", "not_found_error", "NOT_FOUND"])
+        || status == Some(404)
+        || (details.message_contains(&["model"])
+            && details.message_contains(&["not found", "does not exist"]))
+    {
+        return ApiErrorKind::InvalidModel;
+    }
+
+    if status == Some(408) {
+        return ApiErrorKind::Network;
+    }
+
+    if matches!(status, Some(500..=599))
+        || details.has_code(&["overloaded_error", "api_error", "UNAVAILABLE", "INTERNAL"])
+    {
+        return ApiErrorKind::Overloaded;
+    }
+
+    ApiErrorKind::Other
+}
+
+// エラーの原因をたどって分類を判定する
+pub fn error_kind(error: &anyhow::Error) -> Option<ApiErrorKind> {
+    error.chain().find_map(|cause| {
+        if let Some(e) = cause.downcast_ref::<ApiError>() {
+            return Some(e.kind);
+        }
+        if let Some(e) = cause.downcast_ref::<reqwest::Error>() {
+            if e.is_timeout() || e.is_connect() || e.is_request() {
+                return Some(ApiErrorKind::Network);
+            }
+            if e.is_decode() || e.is_body() {
+                return Some(ApiErrorKind::MalformedResponse);
+            }
+        }
+        if cause.downcast_ref::<serde_json::Error>().is_some() {
+            return Some(ApiErrorKind::MalformedResponse);
+        }
+        None
+    })
+}
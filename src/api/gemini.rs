@@ -1,6 +1,10 @@
 use crate::api::GEMINI_API_BASE_ENV;
-use anyhow::{Result, anyhow};
-use reqwest::Client;
+use crate::api::retry::send_with_retry;
+use crate::api::stream::read_events;
+use crate::api::{ApiError, ApiErrorKind, Provider, Request, error_from_response};
+use crate::config::Platform;
+use anyhow::Result;
+use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use std::env;
 
@@ -12,7 +16,9 @@ pub struct GeminiRequest {
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct GeminiContent {
+    #[serde(default)]
     role: String,
+    #[serde(default)]
     parts: Vec<GeminiPart>,
 }
 
@@ -28,68 +34,187 @@ pub struct GeminiGenerationConfig {
 
 #[derive(Deserialize, Debug)]
 pub struct GeminiResponse {
+    #[serde(default)]
     candidates: Vec<GeminiCandidate>,
+    #[serde(default, rename = "promptFeedback")]
+    prompt_feedback: Option<GeminiPromptFeedback>,
 }
 
 #[derive(Deserialize, Debug)]
 pub struct GeminiCandidate {
-    content: GeminiContent,
+    #[serde(default)]
+    content: Option<GeminiContent>,
+    #[serde(default, rename = "finishReason")]
+    finish_reason: Option<String>,
 }
 
-pub async fn call_api(
-    api_key: &str,
-    model: &str,
-    system_prompt: &str,
-    user_prompt: &str,
-) -> Result<String> {
-    let client = Client::new();
-
-    // ベースURLを環境変数から取得（テスト用）
-    let base_url = env::var(GEMINI_API_BASE_ENV)
-        .unwrap_or_else(|_| "https://generativelanguage.googleapis.com".to_string());
-
-    let url = format!(
-        "{}/v1/models/{}:generateContent?key={}",
-        base_url, model, api_key
-    );
-
-    // Geminiはシステムプロンプトとユーザープロンプトを結合する
-    let combined_prompt = format!("{}\n\n{}", system_prompt, user_prompt);
-
-    let request = GeminiRequest {
-        contents: vec![GeminiContent {
-            role: "user".to_string(),
-            parts: vec![GeminiPart {
-                text: combined_prompt,
-            }],
-        }],
-        generation_config: GeminiGenerationConfig {
-            max_output_tokens: 1000,
-        },
-    };
-
-    let response = client
-        .post(url)
-        .header("Content-Type", "application/json")
-        .json(&request)
-        .send()
-        .await?;
-
-    if !response.status().is_success() {
-        let error_text = response.text().await?;
-        return Err(anyhow!("Gemini API error: {}", error_text));
+#[derive(Deserialize, Debug)]
+pub struct GeminiPromptFeedback {
+    #[serde(default, rename = "blockReason")]
+    block_reason: Option<String>,
+}
+
+impl GeminiResponse {
+    // 安全フィルターでブロックされた場合はエラーにする
+    fn check_blocked(&self) -> Result<()> {
+        let reason = self
+            .prompt_feedback
+            .as_ref()
+            .and_then(|f| f.block_reason.clone())
+            .or_else(|| {
+                self.candidates
+                    .first()
+                    .and_then(|c| c.finish_reason.clone())
+                    .filter(|r| matches!(r.as_str(), "SAFETY" | "PROHIBITED_CONTENT" | "BLOCKLIST"))
+            });
+
+        match reason {
+            Some(reason) => Err(ApiError::new(
+                ApiErrorKind::ContentFiltered,
+                "Gemini",
+                format!("The request was blocked ({})", reason),
+            )
+            .into()),
+            None => Ok(()),
+        }
+    }
+}
+
+pub struct GeminiProvider {
+    base_url: Option<String>,
+}
+
+impl GeminiProvider {
+    pub const DEFAULT: Self = Self { base_url: None };
+
+    // 接続先を明示的に指定する（テスト用）
+    #[cfg(test)]
+    pub fn with_base_url(base_url: impl Into<String>) -> Self {
+        Self {
+            base_url: Some(base_url.into()),
+        }
+    }
+
+    fn base_url(&self) -> String {
+        // ベースURLを環境変数から取得（テスト用）
+        self.base_url.clone().unwrap_or_else(|| {
+            env::var(GEMINI_API_BASE_ENV)
+                .unwrap_or_else(|_| "https://generativelanguage.googleapis.com".to_string())
+        })
+    }
+}
+
+#[async_trait]
+impl Provider for GeminiProvider {
+    fn platform(&self) -> Platform {
+        Platform::Gemini
+    }
+
+    fn name(&self) -> &'static str {
+        "Gemini"
+    }
+
+    fn label(&self) -> &'static str {
+        "Gemini (Google)"
+    }
+
+    fn env_var_name(&self) -> &'static str {
+        "GEMINI_API_KEY"
     }
 
-    let gemini_response: GeminiResponse = response.json().await?;
+    fn default_model_name(&self) -> &'static str {
+        "gemini-1.0-pro"
+    }
 
-    if gemini_response.candidates.is_empty() {
-        return Err(anyhow!("Unexpected response format from Gemini API"));
+    // 1Mトークン以上のコンテキストを持つモデルが中心
+    fn token_budget(&self) -> usize {
+        200_000
     }
 
-    let content = &gemini_response.candidates[0].content;
-    if content.parts.is_empty() {
-        return Err(anyhow!("No text in response from Gemini API"));
+    fn models(&self) -> Vec<(&'static str, &'static str)> {
+        vec![
+            ("Gemini 2.0 Flash Lite", "gemini-2.0-flash-lite"),
+            ("Gemini 2.0 Flash", "gemini-2.0-flash"),
+            ("Gemini 1.5 Pro", "gemini-1.5-pro"),
+        ]
     }
 
-    Ok(content.parts[0].text.clone())
+    async fn generate(&self, request: &Request<'_>) -> Result<String> {
+        let client = request.client;
+
+        let base_url = self.base_url();
+
+        // ストリーミングではSSE形式の streamGenerateContent を使う
+        let url = if request.on_token.is_some() {
+            format!(
+                "{}/v1/models/{}:streamGenerateContent?alt=sse",
+                base_url, request.model
+            )
+        } else {
+            format!("{}/v1/models/{}:generateContent", base_url, request.model)
+        };
+
+        // Geminiはシステムプロンプトとユーザープロンプトを結合する
+        let combined_prompt = format!("{}\n\n{}", request.system_prompt, request.user_prompt);
+
+        let body = GeminiRequest {
+            contents: vec![GeminiContent {
+                role: "user".to_string(),
+                parts: vec![GeminiPart {
+                    text: combined_prompt,
+                }],
+            }],
+            generation_config: GeminiGenerationConfig {
+                max_output_tokens: 1000,
+            },
+        };
+
+        // APIキーはURLに含めない（通信エラーのメッセージにURLが表示されるため）
+        let builder = client
+            .post(url)
+            .header("Content-Type", "application/json")
+            .header("x-goog-api-key", request.api_key)
+            .json(&body);
+        let response = send_with_retry(builder, &request.retry, "Gemini").await?;
+
+        if !response.status().is_success() {
+            return Err(error_from_response(response, "Gemini").await);
+        }
+
+        if let Some(on_token) = request.on_token {
+            let mut message = String::new();
+            read_events(response, |data| {
+                let chunk: GeminiResponse = serde_json::from_str(data)?;
+                chunk.check_blocked()?;
+                if let Some(content) = chunk.candidates.first().and_then(|c| c.content.as_ref()) {
+                    for part in &content.parts {
+                        on_token(&part.text);
+                        message.push_str(&part.text);
+                    }
+                }
+                Ok(())
+            })
+            .await?;
+            return Ok(message);
+        }
+
+        let gemini_response: GeminiResponse = response.json().await?;
+
+        gemini_response.check_blocked()?;
+
+        let Some(candidate) = gemini_response.candidates.first() else {
+            return Err(ApiError::malformed(
+                "Gemini",
+                "Unexpected response format from Gemini API",
+            )
+            .into());
+        };
+
+        match candidate.content.as_ref().and_then(|c| c.parts.first()) {
+            Some(part) => Ok(part.text.clone()),
+            None => {
+                Err(ApiError::malformed("Gemini", "No text in response from Gemini API").into())
+            }
+        }
+    }
 }
@@ -1,25 +1,463 @@
+pub mod azure;
 pub mod claude;
+pub mod error;
 pub mod gemini;
+pub mod ollama;
 pub mod openai;
+pub mod openai_compatible;
+pub mod retry;
+mod stream;
 
-use crate::config::Platform;
-use anyhow::Result;
+use crate::config::{
+    AzureConfig, Config, OpenAICompatibleConfig, Platform, RetryConfig, TimeoutConfig,
+};
+pub use error::{ApiError, ApiErrorKind, error_kind};
+
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use futures_util::future::join_all;
+use reqwest::{Client, Response};
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
 
 // ベースURLの環境変数名を定義（テスト用）
 pub const ANTHROPIC_API_BASE_ENV: &str = "ANTHROPIC_API_BASE";
 pub const OPENAI_API_BASE_ENV: &str = "OPENAI_API_BASE";
 pub const GEMINI_API_BASE_ENV: &str = "GEMINI_API_BASE";
+pub const OLLAMA_API_BASE_ENV: &str = "OLLAMA_API_BASE";
 
-pub async fn generate_commit_message(
+// ストリーミングの途中で失敗し、フォールバック先で生成し直す場合に続けて表示する文
+pub const PARTIAL_OUTPUT_NOTICE: &str =
+    "\n[Incomplete output discarded. Generating again with the next provider.]";
+
+// 生成されたテキストを受け取りながら表示するためのコールバック
+pub type TokenCallback<'a> = &'a (dyn Fn(&str) + Send + Sync);
+
+// 試みるプロバイダーに合わせてユーザープロンプトを作り直すためのコールバック（作り直さない場合はNone）
+//   フォールバック先の差分のトークン数の上限が小さい場合に使う
+pub type PromptCallback<'a> = &'a (dyn Fn(&dyn Provider) -> Option<String> + Send + Sync);
+
+// プロバイダーに渡すリクエスト内容
+pub struct Request<'a> {
+    pub api_key: &'a str,
+    pub model: &'a str,
+    pub system_prompt: &'a str,
+    pub user_prompt: &'a str,
+    // 指定された場合、対応するプロバイダーはストリーミングで応答を受け取る
+    pub on_token: Option<TokenCallback<'a>>,
+    // 429や5xxなど一時的なエラーの再試行方法
+    pub retry: RetryConfig,
+    // 実行中に共有するHTTPクライアント
+    pub client: &'a Client,
+    // generate_candidatesで生成する候補の数
+    pub candidates: usize,
+    // generate_with_fallbacksでプロバイダーごとにユーザープロンプトを作り直す
+    pub user_prompt_for: Option<PromptCallback<'a>>,
+}
+
+impl<'a> Request<'a> {
+    // 指定したクライアントを使う空のリクエスト（必要な項目だけを上書きして使う）
+    pub fn new(client: &'a Client) -> Self {
+        Self {
+            api_key: "",
+            model: "",
+            system_prompt: "",
+            user_prompt: "",
+            on_token: None,
+            retry: RetryConfig::default(),
+            client,
+            candidates: 0,
+            user_prompt_for: None,
+        }
+    }
+}
+
+// AIプラットフォームごとの実装が満たすべきインターフェース
+#[async_trait]
+pub trait Provider: Send + Sync {
+    fn platform(&self) -> Platform;
+
+    // 表示名（例: "Claude"）
+    fn name(&self) -> &'static str;
+
+    // 選択肢に表示するラベル（例: "Claude (Anthropic)"）
+    fn label(&self) -> &'static str;
+
+    // APIキーを読み込む環境変数名
+    fn env_var_name(&self) -> &'static str;
+
+    // APIキーなしで利用できるかどうか
+    fn requires_api_key(&self) -> bool {
+        true
+    }
+
+    fn default_model_name(&self) -> &'static str;
+
+    // (表示名, モデルID) のリスト
+    fn models(&self) -> Vec<(&'static str, &'static str)>;
+
+    // 実際に利用可能なモデルの一覧（既定では静的なリストを返す）
+    async fn list_models(&self, _client: &Client) -> Result<Vec<(String, String)>> {
+        Ok(self
+            .models()
+            .into_iter()
+            .map(|(label, id)| (label.to_string(), id.to_string()))
+            .collect())
+    }
+
+    // 英数字の場合の1トークンあたりのおおよその文字数
+    fn chars_per_token(&self) -> f64 {
+        4.0
+    }
+
+    // 差分に使えるトークン数の既定値
+    fn token_budget(&self) -> usize {
+        30_000
+    }
+
+    // テキストのトークン数を見積もる（ASCII以外の文字は1文字1トークンとみなす）
+    fn estimate_tokens(&self, text: &str) -> usize {
+        let (ascii, other) = text.chars().fold((0, 0), |(ascii, other), c| {
+            if c.is_ascii() {
+                (ascii + 1, other)
+            } else {
+                (ascii, other + 1)
+            }
+        });
+        other + (ascii as f64 / self.chars_per_token()).ceil() as usize
+    }
+
+    async fn generate(&self, request: &Request<'_>) -> Result<String>;
+
+    // request.candidates個の候補を生成する（既定では同じリクエストを並行して送る）
+    //   一部が失敗しても、1つでも生成できればその候補を返す
+    async fn generate_candidates(&self, request: &Request<'_>) -> Result<Vec<String>> {
+        let single = Request {
+            on_token: None,
+            ..*request
+        };
+        let results =
+            join_all((0..request.candidates.max(1)).map(|_| self.generate(&single))).await;
+
+        let mut messages = Vec::new();
+        let mut first_error = None;
+        for result in results {
+            match result {
+                Ok(message) => messages.push(message),
+                Err(e) => {
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+        match first_error {
+            Some(e) if messages.is_empty() => Err(e),
+            Some(e) => {
+                eprintln!(
+                    "{} of {} candidates failed: {}",
+                    request.candidates.max(1) - messages.len(),
+                    request.candidates.max(1),
+                    e
+                );
+                Ok(messages)
+            }
+            None => Ok(messages),
+        }
+    }
+}
+
+// プラットフォームと接続設定からプロバイダーを生成する（プロバイダーの一覧はここだけに書く）
+fn build_provider(
     platform: Platform,
-    api_key: &str,
-    model: &str,
-    system_prompt: &str,
-    user_prompt: &str,
-) -> Result<String> {
+    compatible: &OpenAICompatibleConfig,
+    azure: &AzureConfig,
+) -> Box<dyn Provider> {
     match platform {
-        Platform::Claude => claude::call_api(api_key, model, system_prompt, user_prompt).await,
-        Platform::OpenAI => openai::call_api(api_key, model, system_prompt, user_prompt).await,
-        Platform::Gemini => gemini::call_api(api_key, model, system_prompt, user_prompt).await,
+        Platform::Claude => Box::new(claude::ClaudeProvider::DEFAULT),
+        Platform::OpenAI => Box::new(openai::OpenAIProvider::DEFAULT),
+        Platform::Gemini => Box::new(gemini::GeminiProvider::DEFAULT),
+        Platform::Ollama => Box::new(ollama::OllamaProvider::DEFAULT),
+        Platform::OpenAICompatible => Box::new(openai_compatible::OpenAICompatibleProvider::new(
+            compatible.clone(),
+        )),
+        Platform::Azure => Box::new(azure::AzureProvider::new(azure.clone())),
+    }
+}
+
+// 表示名やモデルの一覧などを参照するためのプロバイダー（接続先は未設定）
+pub fn provider(platform: Platform) -> Box<dyn Provider> {
+    build_provider(
+        platform,
+        &OpenAICompatibleConfig::EMPTY,
+        &AzureConfig::EMPTY,
+    )
+}
+
+// 設定内容を反映したプロバイダーを生成する
+pub fn provider_for(platform: Platform, config: &Config) -> Box<dyn Provider> {
+    build_provider(platform, &config.openai_compatible, &config.azure)
+}
+
+// 登録されているすべてのプロバイダーを取得する
+pub fn providers() -> Vec<Box<dyn Provider>> {
+    Platform::ALL
+        .iter()
+        .map(|platform| provider(*platform))
+        .collect()
+}
+
+static CLIENT: OnceLock<Client> = OnceLock::new();
+
+// タイムアウトを設定したHTTPクライアントを生成する
+pub fn build_client(timeout: &TimeoutConfig) -> Result<Client> {
+    let mut builder = Client::builder();
+
+    if timeout.connect_secs > 0 {
+        builder = builder.connect_timeout(Duration::from_secs(timeout.connect_secs));
     }
+    if timeout.read_secs > 0 {
+        builder = builder.read_timeout(Duration::from_secs(timeout.read_secs));
+    }
+    if timeout.total_secs > 0 {
+        builder = builder.timeout(Duration::from_secs(timeout.total_secs));
+    }
+
+    Ok(builder.build()?)
+}
+
+// 実行ごとに一度だけ生成したクライアントを返す
+pub fn shared_client(timeout: &TimeoutConfig) -> Result<&'static Client> {
+    if let Some(client) = CLIENT.get() {
+        return Ok(client);
+    }
+
+    let client = build_client(timeout)?;
+    Ok(CLIENT.get_or_init(|| client))
+}
+
+// タイムアウトによる失敗かどうか
+pub fn is_timeout(error: &anyhow::Error) -> bool {
+    error
+        .chain()
+        .filter_map(|cause| cause.downcast_ref::<reqwest::Error>())
+        .any(|e| e.is_timeout())
+}
+
+// エラーレスポンスの内容からエラーを生成する
+pub(crate) async fn error_from_response(response: Response, api_name: &str) -> anyhow::Error {
+    let status = response.status();
+    match response.text().await {
+        Ok(body) => ApiError::from_response(api_name, status, &body).into(),
+        Err(e) => e.into(),
+    }
+}
+
+// 別のプロバイダーで再挑戦すべきエラーかどうか（一時的な障害・認証エラー・接続失敗）
+pub fn should_fall_back(error: &anyhow::Error) -> bool {
+    matches!(
+        error_kind(error),
+        Some(
+            ApiErrorKind::Auth
+                | ApiErrorKind::RateLimited
+                | ApiErrorKind::Overloaded
+                | ApiErrorKind::Network
+        )
+    )
+}
+
+// 生成を試みるプロバイダーとモデルの組み合わせ
+pub struct Attempt {
+    pub platform: Platform,
+    pub model: String,
+    pub provider: Box<dyn Provider>,
+    // APIキーが見つからない場合はそのエラー
+    pub api_key: Result<String>,
+}
+
+// 生成結果と実際に使用したプロバイダー
+#[derive(Debug)]
+pub struct Generation {
+    pub message: String,
+    // 候補を複数生成した場合はそのすべて（重複は除く、先頭はmessageと同じ）
+    pub candidates: Vec<String>,
+    pub platform: Platform,
+    pub model: String,
+    // 生成に成功したプロバイダーがメッセージをストリーミングで表示したかどうか
+    pub streamed: bool,
+}
+
+// メインのプラットフォームと設定されたフォールバック先を順に並べる
+pub fn attempts(config: &Config) -> Vec<Attempt> {
+    let primary = (config.platform, config.get_model_name());
+    let fallbacks = config.fallbacks.iter().map(|fallback| {
+        let model = fallback
+            .model
+            .clone()
+            .unwrap_or_else(|| config.model_name_for(fallback.platform));
+        (fallback.platform, model)
+    });
+
+    std::iter::once(primary)
+        .chain(fallbacks)
+        .map(|(platform, model)| Attempt {
+            platform,
+            model,
+            provider: provider_for(platform, config),
+            api_key: config.api_key_for(platform),
+        })
+        .collect()
+}
+
+// 失敗した場合は次のプロバイダーで生成を試みる
+pub async fn generate_with_fallbacks(
+    attempts: &[Attempt],
+    template: &Request<'_>,
+) -> Result<Generation> {
+    let mut last_error = anyhow!("No AI platform is configured");
+
+    for (index, attempt) in attempts.iter().enumerate() {
+        let has_next = index + 1 < attempts.len();
+        let label = format!("{} ({})", attempt.platform.as_str(), attempt.model);
+
+        if index > 0 {
+            eprintln!("Falling back to {}...", label);
+        }
+
+        // 途中まで表示したかを記録する（失敗した場合に不完全な出力であることを示すため）
+        let streamed = AtomicBool::new(false);
+        let track = |token: &str| {
+            streamed.store(true, Ordering::Relaxed);
+            if let Some(on_token) = template.on_token {
+                on_token(token);
+            }
+        };
+        let on_token: Option<TokenCallback<'_>> = template.on_token.map(|_| &track as _);
+
+        let result = match &attempt.api_key {
+            Ok(api_key) => {
+                let user_prompt = template
+                    .user_prompt_for
+                    .and_then(|user_prompt_for| user_prompt_for(attempt.provider.as_ref()));
+                let request = Request {
+                    api_key,
+                    model: &attempt.model,
+                    system_prompt: template.system_prompt,
+                    user_prompt: user_prompt.as_deref().unwrap_or(template.user_prompt),
+                    on_token,
+                    retry: template.retry,
+                    client: template.client,
+                    candidates: template.candidates,
+                    user_prompt_for: None,
+                };
+                let result = if template.candidates > 1 {
+                    attempt.provider.generate_candidates(&request).await
+                } else {
+                    attempt
+                        .provider
+                        .generate(&request)
+                        .await
+                        .map(|message| vec![message])
+                };
+                result.map_err(|e| {
+                    if is_timeout(&e) {
+                        e.context(format!(
+                            "Request to {} timed out. Increase it with --timeout or the \"timeout\" config option.",
+                            label
+                        ))
+                    } else {
+                        e
+                    }
+                })
+            }
+            // キーが未設定の場合は認証エラーとして次の候補へ進む
+            Err(e) if has_next => {
+                eprintln!("Skipping {}: {}", label, e);
+                continue;
+            }
+            Err(e) => Err(anyhow!("{}", e)),
+        };
+
+        match result {
+            Ok(messages) => {
+                let candidates = dedupe(messages);
+                return Ok(Generation {
+                    message: candidates.first().cloned().unwrap_or_default(),
+                    candidates,
+                    platform: attempt.platform,
+                    model: attempt.model.clone(),
+                    streamed: streamed.load(Ordering::Relaxed),
+                });
+            }
+            Err(e) if has_next && should_fall_back(&e) => {
+                if streamed.load(Ordering::Relaxed)
+                    && let Some(on_token) = template.on_token
+                {
+                    on_token(&format!("{}\n", PARTIAL_OUTPUT_NOTICE));
+                }
+                eprintln!("{} failed: {}", label, e);
+                last_error = e;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+
+    Err(last_error)
+}
+
+pub async fn generate_commit_message(
+    config: &Config,
+    system_prompt: &str,
+    user_prompt: &str,
+    user_prompt_for: Option<PromptCallback<'_>>,
+    on_token: Option<TokenCallback<'_>>,
+) -> Result<Generation> {
+    let template = Request {
+        system_prompt,
+        user_prompt,
+        user_prompt_for,
+        on_token,
+        retry: config.retry,
+        ..Request::new(shared_client(&config.timeout)?)
+    };
+
+    generate_with_fallbacks(&attempts(config), &template).await
+}
+
+// 複数の候補を生成する（ストリーミングはしない）
+pub async fn generate_candidates(
+    config: &Config,
+    system_prompt: &str,
+    user_prompt: &str,
+    user_prompt_for: Option<PromptCallback<'_>>,
+    count: usize,
+) -> Result<Generation> {
+    let template = Request {
+        system_prompt,
+        user_prompt,
+        user_prompt_for,
+        retry: config.retry,
+        candidates: count,
+        ..Request::new(shared_client(&config.timeout)?)
+    };
+
+    generate_with_fallbacks(&attempts(config), &template).await
+}
+
+// 空白や大文字小文字の違いだけの候補を取り除く（順序は保つ）
+pub fn dedupe(messages: Vec<String>) -> Vec<String> {
+    let mut seen = std::collections::HashSet::new();
+    messages
+        .into_iter()
+        .map(|message| message.trim().to_string())
+        .filter(|message| !message.is_empty())
+        .filter(|message| {
+            seen.insert(
+                message
+                    .split_whitespace()
+                    .collect::<Vec<_>>()
+                    .join(" ")
+                    .to_lowercase(),
+            )
+        })
+        .collect()
 }
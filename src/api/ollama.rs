@@ -0,0 +1,177 @@
+use crate::api::OLLAMA_API_BASE_ENV;
+use crate::api::retry::send_with_retry;
+use crate::api::{ApiError, Provider, Request, error_from_response};
+use crate::config::Platform;
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::env;
+
+#[derive(Serialize)]
+pub struct OllamaRequest {
+    model: String,
+    messages: Vec<OllamaMessage>,
+    stream: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct OllamaMessage {
+    role: String,
+    content: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct OllamaResponse {
+    message: OllamaMessage,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct OllamaTagsResponse {
+    models: Vec<OllamaModel>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct OllamaModel {
+    name: String,
+}
+
+pub struct OllamaProvider {
+    base_url: Option<String>,
+}
+
+impl OllamaProvider {
+    pub const DEFAULT: Self = Self { base_url: None };
+
+    // 接続先を明示的に指定する（テスト用）
+    #[cfg(test)]
+    pub fn with_base_url(base_url: impl Into<String>) -> Self {
+        Self {
+            base_url: Some(base_url.into()),
+        }
+    }
+
+    fn base_url(&self) -> String {
+        // ローカル以外のOllamaサーバーも環境変数で指定できる
+        self.base_url.clone().unwrap_or_else(|| {
+            env::var(OLLAMA_API_BASE_ENV).unwrap_or_else(|_| "http://localhost:11434".to_string())
+        })
+    }
+}
+
+#[async_trait]
+impl Provider for OllamaProvider {
+    fn platform(&self) -> Platform {
+        Platform::Ollama
+    }
+
+    fn name(&self) -> &'static str {
+        "Ollama"
+    }
+
+    fn label(&self) -> &'static str {
+        "Ollama (Local)"
+    }
+
+    fn env_var_name(&self) -> &'static str {
+        "OLLAMA_API_KEY"
+    }
+
+    // ローカル実行のためAPIキーは不要（プロキシ経由の場合のみ使用）
+    fn requires_api_key(&self) -> bool {
+        false
+    }
+
+    fn default_model_name(&self) -> &'static str {
+        "llama3.2"
+    }
+
+    fn chars_per_token(&self) -> f64 {
+        3.5
+    }
+
+    // ローカルモデルは既定のコンテキストが小さい
+    fn token_budget(&self) -> usize {
+        6_000
+    }
+
+    fn models(&self) -> Vec<(&'static str, &'static str)> {
+        vec![
+            ("Llama 3.2", "llama3.2"),
+            ("Qwen 2.5 Coder", "qwen2.5-coder"),
+            ("Mistral", "mistral"),
+        ]
+    }
+
+    async fn list_models(&self, client: &Client) -> Result<Vec<(String, String)>> {
+        let url = format!("{}/api/tags", self.base_url());
+
+        let response = client
+            .get(&url)
+            .send()
+            .await
+            .with_context(|| format!("Could not connect to Ollama at {}", self.base_url()))?;
+
+        if !response.status().is_success() {
+            return Err(error_from_response(response, "Ollama").await);
+        }
+
+        let tags: OllamaTagsResponse = response.json().await?;
+
+        Ok(tags
+            .models
+            .into_iter()
+            .map(|model| (model.name.clone(), model.name))
+            .collect())
+    }
+
+    async fn generate(&self, request: &Request<'_>) -> Result<String> {
+        let client = request.client;
+
+        let url = format!("{}/api/chat", self.base_url());
+
+        let body = OllamaRequest {
+            model: request.model.to_string(),
+            messages: vec![
+                OllamaMessage {
+                    role: "system".to_string(),
+                    content: request.system_prompt.to_string(),
+                },
+                OllamaMessage {
+                    role: "user".to_string(),
+                    content: request.user_prompt.to_string(),
+                },
+            ],
+            stream: false,
+        };
+
+        let mut builder = client
+            .post(url)
+            .header("Content-Type", "application/json")
+            .json(&body);
+
+        if !request.api_key.is_empty() {
+            builder = builder.header("Authorization", format!("Bearer {}", request.api_key));
+        }
+
+        let response = send_with_retry(builder, &request.retry, "Ollama")
+            .await
+            .with_context(|| format!("Could not connect to Ollama at {}", self.base_url()))?;
+
+        if !response.status().is_success() {
+            return Err(error_from_response(response, "Ollama").await);
+        }
+
+        let ollama_response: OllamaResponse = response.json().await?;
+
+        if ollama_response.message.content.is_empty() {
+            return Err(ApiError::malformed(
+                "Ollama",
+                "Unexpected response format from Ollama API",
+            )
+            .into());
+        }
+
+        Ok(ollama_response.message.content)
+    }
+}
@@ -1,6 +1,11 @@
 use crate::api::OPENAI_API_BASE_ENV;
-use anyhow::{Result, anyhow};
-use reqwest::Client;
+use crate::api::retry::send_with_retry;
+use crate::api::stream::read_events;
+use crate::api::{ApiError, ApiErrorKind, Provider, Request, error_from_response};
+use crate::config::Platform;
+use anyhow::Result;
+use async_trait::async_trait;
+use reqwest::RequestBuilder;
 use serde::{Deserialize, Serialize};
 use std::env;
 
@@ -9,6 +14,10 @@ pub struct OpenAIRequest {
     model: String,
     max_tokens: u32,
     messages: Vec<OpenAIMessage>,
+    stream: bool,
+    // 生成する候補の数
+    #[serde(skip_serializing_if = "Option::is_none")]
+    n: Option<usize>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -25,54 +34,227 @@ pub struct OpenAIResponse {
 #[derive(Deserialize, Debug)]
 pub struct OpenAIChoice {
     message: OpenAIMessage,
+    #[serde(default)]
+    finish_reason: Option<String>,
 }
 
-pub async fn call_api(
-    api_key: &str,
-    model: &str,
-    system_prompt: &str,
-    user_prompt: &str,
-) -> Result<String> {
-    let client = Client::new();
+// ストリーミング時に受け取るチャンク
+#[derive(Deserialize, Debug)]
+pub struct OpenAIChunk {
+    choices: Vec<OpenAIChunkChoice>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct OpenAIChunkChoice {
+    delta: OpenAIDelta,
+    #[serde(default)]
+    finish_reason: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct OpenAIDelta {
+    content: Option<String>,
+}
+
+pub struct OpenAIProvider {
+    base_url: Option<String>,
+}
+
+impl OpenAIProvider {
+    pub const DEFAULT: Self = Self { base_url: None };
+
+    // 接続先を明示的に指定する（テスト用）
+    #[cfg(test)]
+    pub fn with_base_url(base_url: impl Into<String>) -> Self {
+        Self {
+            base_url: Some(base_url.into()),
+        }
+    }
+
+    fn base_url(&self) -> String {
+        // ベースURLを環境変数から取得（テスト用）
+        self.base_url.clone().unwrap_or_else(|| {
+            env::var(OPENAI_API_BASE_ENV).unwrap_or_else(|_| "https://api.openai.com".to_string())
+        })
+    }
+}
+
+#[async_trait]
+impl Provider for OpenAIProvider {
+    fn platform(&self) -> Platform {
+        Platform::OpenAI
+    }
+
+    fn name(&self) -> &'static str {
+        "OpenAI"
+    }
+
+    fn label(&self) -> &'static str {
+        "OpenAI (GPT)"
+    }
+
+    fn env_var_name(&self) -> &'static str {
+        "OPENAI_API_KEY"
+    }
+
+    fn default_model_name(&self) -> &'static str {
+        "gpt-4"
+    }
+
+    // 128Kトークンのコンテキストを持つモデルが中心
+    fn token_budget(&self) -> usize {
+        60_000
+    }
+
+    fn models(&self) -> Vec<(&'static str, &'static str)> {
+        vec![
+            ("o4-mini", "o4-mini"),
+            ("GPT-4.1-mini", "gpt-4.1-mini"),
+            ("o3-mini", "o3-mini"),
+            ("GPT-4o-mini", "gpt-4o-mini"),
+        ]
+    }
 
-    // ベースURLを環境変数から取得（テスト用）
-    let base_url =
-        env::var(OPENAI_API_BASE_ENV).unwrap_or_else(|_| "https://api.openai.com".to_string());
-    let url = format!("{}/v1/chat/completions", base_url);
+    async fn generate(&self, request: &Request<'_>) -> Result<String> {
+        let client = request.client;
 
-    let request = OpenAIRequest {
-        model: model.to_string(),
+        let base_url = self.base_url();
+        let url = format!("{}/v1/chat/completions", base_url);
+
+        let builder = client
+            .post(url)
+            .header("Authorization", format!("Bearer {}", request.api_key));
+
+        chat_completion(builder, request, "OpenAI").await
+    }
+
+    async fn generate_candidates(&self, request: &Request<'_>) -> Result<Vec<String>> {
+        let url = format!("{}/v1/chat/completions", self.base_url());
+        let builder = request
+            .client
+            .post(url)
+            .header("Authorization", format!("Bearer {}", request.api_key));
+
+        chat_completion_candidates(builder, request, "OpenAI").await
+    }
+}
+
+fn request_body(request: &Request<'_>, n: Option<usize>) -> OpenAIRequest {
+    OpenAIRequest {
+        model: request.model.to_string(),
         max_tokens: 1000,
         messages: vec![
             OpenAIMessage {
                 role: "system".to_string(),
-                content: system_prompt.to_string(),
+                content: request.system_prompt.to_string(),
             },
             OpenAIMessage {
                 role: "user".to_string(),
-                content: user_prompt.to_string(),
+                content: request.user_prompt.to_string(),
             },
         ],
-    };
+        stream: n.is_none() && request.on_token.is_some(),
+        n,
+    }
+}
 
-    let response = client
-        .post(url)
-        .header("Authorization", format!("Bearer {}", api_key))
+// Chat Completions APIを呼び出す（OpenAI互換のエンドポイントと共用）
+pub(crate) async fn chat_completion(
+    builder: RequestBuilder,
+    request: &Request<'_>,
+    api_name: &str,
+) -> Result<String> {
+    let body = request_body(request, None);
+
+    let builder = builder
         .header("Content-Type", "application/json")
-        .json(&request)
-        .send()
+        .json(&body);
+    let response = send_with_retry(builder, &request.retry, api_name).await?;
+
+    if !response.status().is_success() {
+        return Err(error_from_response(response, api_name).await);
+    }
+
+    if let Some(on_token) = request.on_token {
+        let mut message = String::new();
+        read_events(response, |data| {
+            let chunk: OpenAIChunk = serde_json::from_str(data)?;
+            if let Some(choice) = chunk.choices.first() {
+                check_finish_reason(choice.finish_reason.as_deref(), api_name)?;
+                if let Some(content) = &choice.delta.content {
+                    on_token(content);
+                    message.push_str(content);
+                }
+            }
+            Ok(())
+        })
         .await?;
+        return Ok(message);
+    }
+
+    let openai_response: OpenAIResponse = response.json().await?;
+
+    let Some(choice) = openai_response.choices.first() else {
+        return Err(ApiError::malformed(
+            api_name,
+            format!("Unexpected response format from {} API", api_name),
+        )
+        .into());
+    };
+    check_finish_reason(choice.finish_reason.as_deref(), api_name)?;
+
+    Ok(choice.message.content.clone())
+}
+
+// nパラメーターで複数の候補を1回のリクエストで生成する（ストリーミングはしない）
+pub(crate) async fn chat_completion_candidates(
+    builder: RequestBuilder,
+    request: &Request<'_>,
+    api_name: &str,
+) -> Result<Vec<String>> {
+    let body = request_body(request, Some(request.candidates.max(1)));
+
+    let builder = builder
+        .header("Content-Type", "application/json")
+        .json(&body);
+    let response = send_with_retry(builder, &request.retry, api_name).await?;
 
     if !response.status().is_success() {
-        let error_text = response.text().await?;
-        return Err(anyhow!("OpenAI API error: {}", error_text));
+        return Err(error_from_response(response, api_name).await);
     }
 
     let openai_response: OpenAIResponse = response.json().await?;
 
-    if openai_response.choices.is_empty() {
-        return Err(anyhow!("Unexpected response format from OpenAI API"));
+    // フィルターで止められた候補は除き、すべて止められた場合のみエラーにする
+    let mut messages = Vec::new();
+    let mut filtered = None;
+    for choice in openai_response.choices {
+        match check_finish_reason(choice.finish_reason.as_deref(), api_name) {
+            Ok(()) => messages.push(choice.message.content),
+            Err(e) => filtered = Some(e),
+        }
     }
+    if messages.is_empty() {
+        return Err(filtered.unwrap_or_else(|| {
+            ApiError::malformed(
+                api_name,
+                format!("Unexpected response format from {} API", api_name),
+            )
+            .into()
+        }));
+    }
+    Ok(messages)
+}
 
-    Ok(openai_response.choices[0].message.content.clone())
+// コンテンツフィルターで生成が打ち切られた場合はエラーにする
+fn check_finish_reason(finish_reason: Option<&str>, api_name: &str) -> Result<()> {
+    if finish_reason == Some("content_filter") {
+        return Err(ApiError::new(
+            ApiErrorKind::ContentFiltered,
+            api_name,
+            "The response was blocked by the content filter",
+        )
+        .into());
+    }
+    Ok(())
 }
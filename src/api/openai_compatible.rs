@@ -0,0 +1,95 @@
+use crate::api::openai::chat_completion;
+use crate::api::{Provider, Request};
+use crate::config::{OpenAICompatibleConfig, Platform};
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+
+// vLLM, LM Studio, LiteLLM, Groq, DeepSeek などOpenAI互換のエンドポイント
+pub struct OpenAICompatibleProvider {
+    settings: OpenAICompatibleConfig,
+}
+
+impl OpenAICompatibleProvider {
+    pub fn new(settings: OpenAICompatibleConfig) -> Self {
+        Self { settings }
+    }
+
+    fn base_url(&self) -> Result<&str> {
+        let base_url = self.settings.base_url.trim_end_matches('/');
+        if base_url.is_empty() {
+            return Err(anyhow!(
+                "OpenAI-compatible endpoint is not configured. Please set it with 'aic config --api'"
+            ));
+        }
+        Ok(base_url)
+    }
+
+    // 認証ヘッダーと追加ヘッダーを付与する
+    fn with_headers(
+        &self,
+        mut builder: reqwest::RequestBuilder,
+        api_key: &str,
+    ) -> reqwest::RequestBuilder {
+        if !api_key.is_empty() {
+            let value = if self.settings.auth_scheme.is_empty() {
+                api_key.to_string()
+            } else {
+                format!("{} {}", self.settings.auth_scheme, api_key)
+            };
+            builder = builder.header(self.settings.auth_header.as_str(), value);
+        }
+
+        for (name, value) in &self.settings.extra_headers {
+            builder = builder.header(name.as_str(), value.as_str());
+        }
+
+        builder
+    }
+}
+
+#[async_trait]
+impl Provider for OpenAICompatibleProvider {
+    fn platform(&self) -> Platform {
+        Platform::OpenAICompatible
+    }
+
+    fn name(&self) -> &'static str {
+        "OpenAI-compatible"
+    }
+
+    fn label(&self) -> &'static str {
+        "OpenAI-compatible (vLLM, LM Studio, LiteLLM, ...)"
+    }
+
+    fn env_var_name(&self) -> &'static str {
+        "OPENAI_COMPATIBLE_API_KEY"
+    }
+
+    // ローカルのサーバーなど認証不要な接続先もある
+    fn requires_api_key(&self) -> bool {
+        false
+    }
+
+    fn default_model_name(&self) -> &'static str {
+        ""
+    }
+
+    fn models(&self) -> Vec<(&'static str, &'static str)> {
+        vec![]
+    }
+
+    async fn generate(&self, request: &Request<'_>) -> Result<String> {
+        if request.model.is_empty() {
+            return Err(anyhow!(
+                "No model is configured for the OpenAI-compatible endpoint. Please set it with 'aic config --api'"
+            ));
+        }
+
+        let client = request.client;
+        let url = format!("{}/chat/completions", self.base_url()?);
+
+        let builder = self.with_headers(client.post(url), request.api_key);
+
+        chat_completion(builder, request, "OpenAI-compatible").await
+    }
+}
@@ -0,0 +1,107 @@
+use crate::config::RetryConfig;
+use anyhow::{Result, anyhow};
+use reqwest::header::HeaderMap;
+use reqwest::{RequestBuilder, Response, StatusCode};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// 一時的な障害とみなして再試行するステータスコード
+pub fn is_retryable_status(status: StatusCode) -> bool {
+    // 529はAnthropicの "overloaded" エラー
+    matches!(status.as_u16(), 408 | 429 | 500 | 502 | 503 | 504 | 529)
+}
+
+// 指数バックオフによる待機時間（attemptは1始まり）
+pub fn backoff_delay(policy: &RetryConfig, attempt: u32) -> Duration {
+    let exponent = attempt.saturating_sub(1).min(16);
+    let delay = policy
+        .initial_backoff_ms
+        .saturating_mul(1 << exponent)
+        .min(policy.max_backoff_ms);
+
+    // 同時に再試行が集中しないよう、待機時間の後半をランダムにずらす
+    let delay = if policy.jitter && delay > 1 {
+        let half = delay / 2;
+        half + random_below(delay - half)
+    } else {
+        delay
+    };
+
+    Duration::from_millis(delay)
+}
+
+// Retry-After（秒）または retry-after-ms ヘッダーから待機時間を取得する
+pub fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+
+    let seconds = |v: &str| v.trim().parse::<f64>().ok().filter(|n| n.is_finite());
+    // Durationに収まらない大きな値（例: 1e300）は上限の値として扱う
+    let duration = |secs: f64| Duration::try_from_secs_f64(secs.max(0.0)).unwrap_or(Duration::MAX);
+
+    if let Some(ms) = header("retry-after-ms").and_then(seconds) {
+        return Some(duration(ms / 1000.0));
+    }
+
+    header("retry-after").and_then(seconds).map(duration)
+}
+
+// 再試行までの待機時間（サーバーの指定はmax_backoff_msを上限とする）
+pub fn retry_delay(policy: &RetryConfig, headers: &HeaderMap, attempt: u32) -> Duration {
+    match retry_after(headers) {
+        Some(delay) => delay.min(Duration::from_millis(policy.max_backoff_ms)),
+        None => backoff_delay(policy, attempt),
+    }
+}
+
+// リクエストを送信し、一時的なエラーの場合は設定に従って再試行する
+pub(crate) async fn send_with_retry(
+    builder: RequestBuilder,
+    policy: &RetryConfig,
+    api_name: &str,
+) -> Result<Response> {
+    let max_attempts = policy.max_attempts.max(1);
+    let mut attempt = 1;
+
+    loop {
+        let request = builder
+            .try_clone()
+            .ok_or_else(|| anyhow!("{} API request cannot be retried", api_name))?;
+        let outcome = request.send().await;
+
+        let (reason, delay) = match &outcome {
+            Ok(response) if is_retryable_status(response.status()) => (
+                format!("returned {}", response.status()),
+                retry_delay(policy, response.headers(), attempt),
+            ),
+            Err(e) if e.is_connect() || e.is_timeout() => (
+                format!("request failed ({})", e),
+                backoff_delay(policy, attempt),
+            ),
+            _ => return Ok(outcome?),
+        };
+
+        if attempt >= max_attempts {
+            return Ok(outcome?);
+        }
+
+        eprintln!(
+            "{} API {}. Retrying in {:.1}s (attempt {}/{})...",
+            api_name,
+            reason,
+            delay.as_secs_f64(),
+            attempt + 1,
+            max_attempts
+        );
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+    }
+}
+
+// ジッター用の簡易な乱数（暗号用途ではない）
+fn random_below(bound: u64) -> u64 {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.subsec_nanos() as u64)
+        .unwrap_or(0);
+    let mixed = nanos.wrapping_mul(0x9E37_79B9_7F4A_7C15).rotate_left(17);
+    mixed % bound.max(1)
+}
@@ -0,0 +1,40 @@
+use anyhow::Result;
+use reqwest::Response;
+
+// Server-Sent Eventsのレスポンスを読み取り、data行ごとにコールバックを呼ぶ
+pub(crate) async fn read_events(
+    mut response: Response,
+    mut on_data: impl FnMut(&str) -> Result<()>,
+) -> Result<()> {
+    let mut buffer: Vec<u8> = Vec::new();
+
+    while let Some(chunk) = response.chunk().await? {
+        buffer.extend_from_slice(&chunk);
+
+        // 行単位で処理し、途中までの行はバッファに残す
+        while let Some(pos) = buffer.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = buffer.drain(..=pos).collect();
+            handle_line(&String::from_utf8_lossy(&line), &mut on_data)?;
+        }
+    }
+
+    if !buffer.is_empty() {
+        handle_line(&String::from_utf8_lossy(&buffer), &mut on_data)?;
+    }
+
+    Ok(())
+}
+
+fn handle_line(line: &str, on_data: &mut impl FnMut(&str) -> Result<()>) -> Result<()> {
+    let line = line.trim_end_matches(['\r', '\n']);
+
+    if let Some(data) = line.strip_prefix("data:") {
+        let data = data.trim_start();
+        // OpenAIは終端を "[DONE]" で通知する
+        if !data.is_empty() && data != "[DONE]" {
+            on_data(data)?;
+        }
+    }
+
+    Ok(())
+}
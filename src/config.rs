@@ -1,187 +1,1031 @@
+use crate::api;
+use crate::credentials::CredentialStore;
 use crate::language::Language;
-use anyhow::{Result, anyhow};
+use anyhow::{Context, Result, anyhow};
 use promptuity::{
     Promptuity, Term,
-    prompts::{Select, SelectOption},
+    prompts::{Input, Select, SelectOption},
     themes::FancyTheme,
 };
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fmt;
 use std::fs::{self, File, OpenOptions};
 use std::io::{self, Read, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::process::Command;
 
-#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
 pub enum Platform {
+    #[default]
     Claude,
     OpenAI,
     Gemini,
+    Ollama,
+    OpenAICompatible,
+    Azure,
 }
 
 impl Platform {
+    // 選択肢として表示する順序
+    pub const ALL: [Platform; 6] = [
+        Platform::Claude,
+        Platform::OpenAI,
+        Platform::Gemini,
+        Platform::Ollama,
+        Platform::OpenAICompatible,
+        Platform::Azure,
+    ];
+
     pub fn as_str(&self) -> &'static str {
-        match self {
-            Platform::Claude => "Claude",
-            Platform::OpenAI => "OpenAI",
-            Platform::Gemini => "Gemini",
-        }
+        api::provider(*self).name()
     }
 
     pub fn env_var_name(&self) -> &'static str {
-        match self {
-            Platform::Claude => "CLAUDE_API_KEY",
-            Platform::OpenAI => "OPENAI_API_KEY",
-            Platform::Gemini => "GEMINI_API_KEY",
-        }
+        api::provider(*self).env_var_name()
     }
 
     pub fn default_model_name(&self) -> &'static str {
-        match self {
-            Platform::Claude => "claude-3-opus-20240229",
-            Platform::OpenAI => "gpt-4",
-            Platform::Gemini => "gemini-1.0-pro",
-        }
+        api::provider(*self).default_model_name()
+    }
+
+    pub fn requires_api_key(&self) -> bool {
+        api::provider(*self).requires_api_key()
     }
 
     pub fn get_models(&self) -> Vec<(&'static str, &'static str)> {
+        api::provider(*self).models()
+    }
+
+    // 設定ファイルの api_keys の項目名（キーリングのアカウント名にも使う）
+    pub fn key_name(&self) -> &'static str {
         match self {
-            Platform::Claude => vec![
-                ("Claude 3.7 Sonnet", "claude-3-7-sonnet-20250219"),
-                ("Claude 3.5 Sonnet	", "claude-3-5-sonnet-20240620"),
-                ("Claude 3.5 Haiku", "claude-3-5-haiku-20241022"),
-            ],
-            Platform::OpenAI => vec![
-                ("o4-mini", "o4-mini"),
-                ("GPT-4.1-mini", "gpt-4.1-mini"),
-                ("o3-mini", "o3-mini"),
-                ("GPT-4o-mini", "gpt-4o-mini"),
-            ],
-            Platform::Gemini => vec![
-                ("Gemini 2.0 Flash Lite", "gemini-2.0-flash-lite"),
-                ("Gemini 2.0 Flash", "gemini-2.0-flash"),
-                ("Gemini 1.5 Pro", "gemini-1.5-pro"),
-            ],
-        }
-    }
-}
-
-impl Default for Platform {
-    fn default() -> Self {
-        Platform::Claude
+            Platform::Claude => "claude",
+            Platform::OpenAI => "openai",
+            Platform::Gemini => "gemini",
+            Platform::Ollama => "ollama",
+            Platform::OpenAICompatible => "openai_compatible",
+            Platform::Azure => "azure",
+        }
     }
+
+    // 名前からプラットフォームを取得する（大文字小文字や記号の違いは無視）
+    pub fn from_name(name: &str) -> Result<Platform> {
+        let wanted = normalize_name(name);
+        Platform::ALL
+            .into_iter()
+            .find(|platform| {
+                normalize_name(platform.as_str()) == wanted
+                    || normalize_name(&format!("{:?}", platform)) == wanted
+            })
+            .ok_or_else(|| {
+                anyhow!(
+                    "Unknown platform: {}. Available platforms: {}",
+                    name,
+                    Platform::ALL
+                        .iter()
+                        .map(|platform| platform.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            })
+    }
+}
+
+// 比較用に英数字のみを小文字で残す
+pub fn normalize_name(name: &str) -> String {
+    name.chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+// 設定ファイルに保存したAPIキー（Platform::key_nameをキーにする）
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(transparent)]
 pub struct ApiKeys {
-    pub claude: Option<String>,
-    pub openai: Option<String>,
-    pub gemini: Option<String>,
+    #[serde(deserialize_with = "deserialize_api_keys")]
+    keys: BTreeMap<String, String>,
+}
+
+// 未設定のキー（null）は読み飛ばす
+fn deserialize_api_keys<'de, D>(deserializer: D) -> Result<BTreeMap<String, String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let keys = BTreeMap::<String, Option<String>>::deserialize(deserializer)?;
+    Ok(keys
+        .into_iter()
+        .filter_map(|(name, key)| Some((name, key?)))
+        .collect())
 }
 
 impl ApiKeys {
     pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get_key(&self, platform: Platform) -> Option<String> {
+        self.keys.get(platform.key_name()).cloned()
+    }
+
+    pub fn remove_key(&mut self, platform: Platform) -> Option<String> {
+        self.keys.remove(platform.key_name())
+    }
+
+    pub fn set_key(&mut self, platform: Platform, key: String) {
+        self.keys.insert(platform.key_name().to_string(), key);
+    }
+}
+
+// OpenAI互換エンドポイントの接続設定
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct OpenAICompatibleConfig {
+    // `/chat/completions` の手前までのURL（例: http://localhost:8000/v1）
+    pub base_url: String,
+    pub auth_header: String,
+    // 空文字の場合はAPIキーをそのままヘッダーに設定する
+    pub auth_scheme: String,
+    pub extra_headers: BTreeMap<String, String>,
+    pub model: Option<String>,
+}
+
+impl OpenAICompatibleConfig {
+    pub const EMPTY: Self = Self {
+        base_url: String::new(),
+        auth_header: String::new(),
+        auth_scheme: String::new(),
+        extra_headers: BTreeMap::new(),
+        model: None,
+    };
+}
+
+impl Default for OpenAICompatibleConfig {
+    fn default() -> Self {
         Self {
-            claude: None,
-            openai: None,
-            gemini: None,
+            auth_header: "Authorization".to_string(),
+            auth_scheme: "Bearer".to_string(),
+            ..Self::EMPTY
         }
     }
+}
 
-    pub fn get_key(&self, platform: Platform) -> Option<String> {
-        match platform {
-            Platform::Claude => self.claude.clone(),
-            Platform::OpenAI => self.openai.clone(),
-            Platform::Gemini => self.gemini.clone(),
+// Azure OpenAIのデプロイメント設定
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct AzureConfig {
+    // リソース名（例: my-resource）またはエンドポイントURL
+    pub resource: String,
+    // デプロイメント名（モデル名として扱う）
+    pub deployment: String,
+    pub api_version: String,
+}
+
+impl AzureConfig {
+    pub const EMPTY: Self = Self {
+        resource: String::new(),
+        deployment: String::new(),
+        api_version: String::new(),
+    };
+
+    pub const DEFAULT_API_VERSION: &'static str = "2024-10-21";
+
+    // リソース名からエンドポイントURLを組み立てる
+    pub fn endpoint(&self) -> String {
+        if self.resource.contains("://") {
+            self.resource.trim_end_matches('/').to_string()
+        } else {
+            format!("https://{}.openai.azure.com", self.resource)
         }
     }
+}
 
-    pub fn set_key(&mut self, platform: Platform, key: String) {
-        match platform {
-            Platform::Claude => self.claude = Some(key),
-            Platform::OpenAI => self.openai = Some(key),
-            Platform::Gemini => self.gemini = Some(key),
+impl Default for AzureConfig {
+    fn default() -> Self {
+        Self {
+            api_version: Self::DEFAULT_API_VERSION.to_string(),
+            ..Self::EMPTY
+        }
+    }
+}
+
+// 429や5xxなど一時的なAPIエラーの再試行設定
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(default)]
+pub struct RetryConfig {
+    // 最初のリクエストを含む最大試行回数
+    pub max_attempts: u32,
+    pub initial_backoff_ms: u64,
+    pub max_backoff_ms: u64,
+    pub jitter: bool,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff_ms: 1000,
+            max_backoff_ms: 30000,
+            jitter: true,
+        }
+    }
+}
+
+// HTTPリクエストのタイムアウト設定（秒、0で無制限）
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(default)]
+pub struct TimeoutConfig {
+    pub connect_secs: u64,
+    // 応答の受信が途切れてから待つ時間
+    pub read_secs: u64,
+    // リクエスト全体にかけられる時間
+    pub total_secs: u64,
+}
+
+impl Default for TimeoutConfig {
+    fn default() -> Self {
+        Self {
+            connect_secs: 10,
+            read_secs: 60,
+            total_secs: 120,
+        }
+    }
+}
+
+// 送信する差分の大きさの設定
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct DiffConfig {
+    // 差分に使えるトークン数（省略時はプラットフォームごとの既定値、0で無制限）
+    pub max_tokens: Option<usize>,
+    // 上限を超えたときに1つのハンクに残す行数（0で無制限）
+    pub max_hunk_lines: usize,
+    // 上限を超えたときに切り詰める代わりに分割して要約する
+    pub summarize: bool,
+    // 要約する1回分の差分のトークン数（0でファイルごと）
+    pub chunk_tokens: usize,
+    // 同時に実行する要約リクエストの数
+    pub concurrency: usize,
+    // 差分の内容を送らず、変更行数だけを伝えるファイルのパターン
+    pub exclude: Vec<String>,
+}
+
+impl Default for DiffConfig {
+    fn default() -> Self {
+        Self {
+            max_tokens: None,
+            max_hunk_lines: 200,
+            summarize: false,
+            chunk_tokens: 8000,
+            concurrency: 4,
+            exclude: default_exclude(),
+        }
+    }
+}
+
+// Conventional Commits形式のメッセージの設定
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct ConventionalConfig {
+    pub enabled: bool,
+    // 使用できるtype
+    pub types: Vec<String>,
+    // 使用できるscope（空ならscopeを制限しない）
+    pub scopes: Vec<String>,
+    // 形式に合わない場合に生成し直す回数
+    pub retries: u32,
+    // パスのパターンとscopeの対応（例: "crates/parser/**" = "parser"）
+    pub path_scopes: BTreeMap<String, String>,
+}
+
+impl Default for ConventionalConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            types: [
+                "feat", "fix", "docs", "style", "refactor", "perf", "test", "build", "ci", "chore",
+                "revert",
+            ]
+            .iter()
+            .map(|t| t.to_string())
+            .collect(),
+            scopes: Vec::new(),
+            retries: 2,
+            path_scopes: BTreeMap::new(),
         }
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+// APIキーの保存先
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyStorage {
+    // OSのキーリングを使い、使えない場合は暗号化したファイルに保存する
+    #[default]
+    Auto,
+    Keyring,
+    File,
+    // 設定ファイルに平文で保存する（以前の動作）
+    Config,
+}
+
+// 差分に秘密情報が含まれていたときの対応
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SecretAction {
+    // 伏せ字にして送信する
+    #[default]
+    Redact,
+    // 送信せずに中止する
+    Abort,
+}
+
+// 送信前の秘密情報の検出設定
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(default)]
+pub struct SecretsConfig {
+    pub action: SecretAction,
+    // 秘密情報とみなさない値の正規表現
+    pub allowlist: Vec<String>,
+    // 検査しないファイルのパターン（テスト用のダミーキーなど）
+    pub allow_paths: Vec<String>,
+}
+
+// ロックファイルや圧縮済みのファイルは既定で除外する
+fn default_exclude() -> Vec<String> {
+    [
+        "Cargo.lock",
+        "package-lock.json",
+        "yarn.lock",
+        "pnpm-lock.yaml",
+        "*.min.js",
+        "*.min.css",
+    ]
+    .iter()
+    .map(|pattern| pattern.to_string())
+    .collect()
+}
+
+// メインのプラットフォームが失敗したときに順に試す代替先
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FallbackConfig {
+    pub platform: Platform,
+    // 省略時はプラットフォームの既定モデルを使う
+    #[serde(default)]
+    pub model: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Config {
+    // 設定ファイルの形式のバージョン（古い形式は読み込み時に移行する）
+    #[serde(default)]
+    pub version: u32,
+    // 平文で保存されたAPIキー（key_storageが"config"の場合と、移行前の設定ファイル）
+    #[serde(default)]
     pub api_keys: ApiKeys,
+    #[serde(default)]
+    pub key_storage: KeyStorage,
+    #[serde(default)]
     pub language: Language,
+    #[serde(default)]
     pub platform: Platform,
+    #[serde(default)]
     pub selected_model: Option<String>,
+    #[serde(default)]
     pub custom_prompt: Option<String>,
+    #[serde(default)]
+    pub openai_compatible: OpenAICompatibleConfig,
+    #[serde(default)]
+    pub azure: AzureConfig,
+    // 生成中のメッセージを逐次表示する
+    #[serde(default = "default_stream")]
+    pub stream: bool,
+    #[serde(default)]
+    pub retry: RetryConfig,
+    #[serde(default)]
+    pub timeout: TimeoutConfig,
+    #[serde(default)]
+    pub fallbacks: Vec<FallbackConfig>,
+    #[serde(default)]
+    pub diff: DiffConfig,
+    #[serde(default)]
+    pub secrets: SecretsConfig,
+    #[serde(default)]
+    pub conventional: ConventionalConfig,
+    // 名前付きのプロファイル（グローバルの設定に重ねる部分的な設定）
+    #[serde(default)]
+    pub profiles: BTreeMap<String, Value>,
+    // `aic config profile use` で選択したプロファイル
+    #[serde(default)]
+    pub active_profile: Option<String>,
+    // 各設定値の読み込み元（保存はしない）
+    #[serde(skip)]
+    pub sources: ConfigSources,
+}
+
+// 現在の設定ファイルの形式のバージョン
+pub const CONFIG_VERSION: u32 = 1;
+
+// バージョンごとの移行処理（MIGRATIONS[n] でバージョンnからn+1に移行する）
+const MIGRATIONS: [fn(&mut Value); CONFIG_VERSION as usize] = [migrate_v0_to_v1];
+
+// バージョン0（versionのない設定ファイル）から1へ
+//   以前は入力を省略したAPIキーも空文字列として保存していたため、未設定に戻す
+fn migrate_v0_to_v1(config: &mut Value) {
+    if let Some(keys) = config.get_mut("api_keys").and_then(Value::as_object_mut) {
+        for key in keys.values_mut() {
+            if key.as_str() == Some("") {
+                *key = Value::Null;
+            }
+        }
+    }
+}
+
+// 設定ファイルを読み込み、古い形式であれば現在の形式に移行する
+//   壊れた設定ファイルを既定値で上書きしないように、読み込めない場合はエラーにする
+pub fn parse_config(contents: &str, path: &Path) -> Result<Config> {
+    let invalid = || format!("Invalid configuration file {}", path.display());
+
+    let mut value: Value = serde_json::from_str(contents)
+        .map_err(|e| anyhow!("{}", e))
+        .with_context(invalid)?;
+    let version = match value.get("version") {
+        None => 0,
+        Some(version) => version
+            .as_u64()
+            .and_then(|v| u32::try_from(v).ok())
+            .ok_or_else(|| anyhow!("version must be a number"))
+            .with_context(invalid)?,
+    };
+
+    if version > CONFIG_VERSION {
+        return Err(anyhow!(
+            "{} was written by a newer version of aic (configuration version {}, supported up to {}). Please update aic.",
+            path.display(),
+            version,
+            CONFIG_VERSION
+        ));
+    }
+
+    if version == CONFIG_VERSION {
+        // 移行が不要な場合は元のテキストから読み込み、エラーの行と列を示す
+        let mut deserializer = serde_json::Deserializer::from_str(contents);
+        return serde_path_to_error::deserialize(&mut deserializer)
+            .map_err(path_error)
+            .with_context(invalid);
+    }
+
+    for migrate in &MIGRATIONS[version as usize..] {
+        migrate(&mut value);
+    }
+    if let Some(map) = value.as_object_mut() {
+        map.insert("version".to_string(), Value::from(CONFIG_VERSION));
+    }
+    config_from_value(value).with_context(invalid)
+}
+
+fn config_from_value(value: Value) -> Result<Config> {
+    serde_path_to_error::deserialize(value).map_err(path_error)
+}
+
+// どの項目が不正かを含むエラーにする
+//   APIのレスポンスのエラーと区別するため、serde_json::Error は文字列にする
+fn path_error(error: serde_path_to_error::Error<serde_json::Error>) -> anyhow::Error {
+    let path = error.path().to_string();
+    let inner = error.into_inner();
+    if path == "." {
+        anyhow!("{}", inner)
+    } else {
+        anyhow!("`{}`: {}", path, inner)
+    }
+}
+
+// 使用するプロファイルを指定する環境変数
+pub const PROFILE_ENV: &str = "AIC_PROFILE";
+
+// リポジトリのトップレベルに置く設定ファイル（先に見つかったものを使う）
+pub const REPO_CONFIG_FILES: [&str; 2] = [".aic.toml", ".aic.json"];
+
+// 設定値の読み込み元
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigSource {
+    Default,
+    Global(PathBuf),
+    Profile(String),
+    Repo(PathBuf),
+}
+
+impl fmt::Display for ConfigSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigSource::Default => write!(f, "default"),
+            ConfigSource::Global(path) => write!(f, "global: {}", path.display()),
+            ConfigSource::Profile(name) => write!(f, "profile: {}", name),
+            ConfigSource::Repo(path) => write!(f, "repo: {}", path.display()),
+        }
+    }
+}
+
+// 設定ファイルごとに、書かれていたキー（"diff.max_tokens" の形式）を記録する
+#[derive(Debug, Clone, Default)]
+pub struct ConfigSources {
+    global: Option<(PathBuf, Vec<String>)>,
+    profile: Option<(String, Vec<String>)>,
+    repo: Option<(PathBuf, Vec<String>)>,
+}
+
+impl ConfigSources {
+    // キーの値がどこから来たか（リポジトリ > プロファイル > グローバル > 既定値の順）
+    pub fn source_of(&self, key: &str) -> ConfigSource {
+        let defines = |keys: &Vec<String>| {
+            keys.iter().any(|k| {
+                k == key
+                    || k.starts_with(&format!("{}.", key))
+                    || key.starts_with(&format!("{}.", k))
+            })
+        };
+
+        if let Some((path, keys)) = &self.repo
+            && defines(keys)
+        {
+            return ConfigSource::Repo(path.clone());
+        }
+        if let Some((name, keys)) = &self.profile
+            && defines(keys)
+        {
+            return ConfigSource::Profile(name.clone());
+        }
+        if let Some((path, keys)) = &self.global
+            && defines(keys)
+        {
+            return ConfigSource::Global(path.clone());
+        }
+        ConfigSource::Default
+    }
+
+    pub fn profile_name(&self) -> Option<&str> {
+        self.profile.as_ref().map(|(name, _)| name.as_str())
+    }
+
+    pub fn repo_path(&self) -> Option<&Path> {
+        self.repo.as_ref().map(|(path, _)| path.as_path())
+    }
+}
+
+// 値に含まれる末端のキーを列挙する
+fn leaf_keys(value: &Value, prefix: &str, keys: &mut Vec<String>) {
+    match value {
+        Value::Object(map) if !map.is_empty() => {
+            for (name, child) in map {
+                let key = if prefix.is_empty() {
+                    name.clone()
+                } else {
+                    format!("{}.{}", prefix, name)
+                };
+                leaf_keys(child, &key, keys);
+            }
+        }
+        _ if !prefix.is_empty() => keys.push(prefix.to_string()),
+        _ => {}
+    }
+}
+
+// overlayの値でbaseを上書きする（オブジェクトはキーごとに再帰的にマージ）
+fn merge(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Object(base), Value::Object(overlay)) => {
+            for (name, value) in overlay {
+                match base.get_mut(&name) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        base.insert(name, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+// Gitリポジトリのトップレベルのディレクトリ
+pub fn get_repo_root() -> Option<PathBuf> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--show-toplevel"])
+        .output()
+        .ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+
+    let root = String::from_utf8(output.stdout).ok()?;
+    Some(PathBuf::from(root.trim_end()))
+}
+
+// リポジトリの設定ファイルを探す
+pub fn find_repo_config(root: &Path) -> Option<PathBuf> {
+    REPO_CONFIG_FILES
+        .iter()
+        .map(|name| root.join(name))
+        .find(|path| path.is_file())
+}
+
+// リポジトリの設定ファイルをJSONの値として読み込む
+fn read_repo_config(path: &Path) -> Result<Value> {
+    let contents =
+        fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+
+    let value: Value = if path.extension().is_some_and(|ext| ext == "toml") {
+        let table: toml::Value = toml::from_str(&contents)
+            .map_err(|e| anyhow!("{}", e))
+            .with_context(|| format!("Failed to parse {}", path.display()))?;
+        serde_json::to_value(table)?
+    } else {
+        serde_json::from_str(&contents)
+            .map_err(|e| anyhow!("{}", e))
+            .with_context(|| format!("Failed to parse {}", path.display()))?
+    };
+
+    check_repo_keys(&value, path)?;
+    Ok(value)
+}
+
+// リポジトリの設定ファイルで変更できる項目（"diff" はその下のすべての項目）
+//   クローンしたリポジトリに置かれたファイルで、APIキーの送り先や秘密情報の扱いを変えられないようにする
+//   selected_model はグローバルの設定やプロファイルで選んだプラットフォームにのみ適用される
+const REPO_CONFIG_KEYS: [&str; 5] = [
+    "language",
+    "custom_prompt",
+    "selected_model",
+    "diff",
+    "conventional",
+];
+
+fn check_repo_keys(value: &Value, path: &Path) -> Result<()> {
+    if !value.is_object() {
+        return Err(anyhow!(
+            "Invalid configuration in {}: expected a table of settings",
+            path.display()
+        ));
+    }
+
+    let mut keys = Vec::new();
+    leaf_keys(value, "", &mut keys);
+    let rejected: Vec<String> = keys
+        .into_iter()
+        .filter(|key| {
+            let section = key.split('.').next().unwrap_or_default();
+            !REPO_CONFIG_KEYS.contains(&section)
+        })
+        .collect();
+    if rejected.is_empty() {
+        return Ok(());
+    }
+
+    Err(anyhow!(
+        "{} sets options that a repository configuration cannot change: {}. Only {} can be set per repository. Move the other options to the global configuration or a profile.",
+        path.display(),
+        rejected.join(", "),
+        REPO_CONFIG_KEYS
+            .iter()
+            .map(|key| match *key {
+                "diff" | "conventional" => format!("{}.*", key),
+                key => key.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    ))
+}
+
+fn profile_names(config: &Config) -> String {
+    if config.profiles.is_empty() {
+        return "(none)".to_string();
+    }
+    config
+        .profiles
+        .keys()
+        .cloned()
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn default_stream() -> bool {
+    true
 }
 
 impl Config {
     pub fn new() -> Self {
         Self {
+            version: CONFIG_VERSION,
             api_keys: ApiKeys::new(),
+            key_storage: KeyStorage::default(),
             language: Language::default(),
             platform: Platform::default(),
             selected_model: None,
             custom_prompt: None,
+            openai_compatible: OpenAICompatibleConfig::default(),
+            azure: AzureConfig::default(),
+            stream: default_stream(),
+            retry: RetryConfig::default(),
+            timeout: TimeoutConfig::default(),
+            fallbacks: Vec::new(),
+            diff: DiffConfig::default(),
+            secrets: SecretsConfig::default(),
+            conventional: ConventionalConfig::default(),
+            profiles: BTreeMap::new(),
+            active_profile: None,
+            sources: ConfigSources::default(),
         }
     }
 
-    pub fn load() -> Result<Self> {
-        let config_path = get_config_path()?;
+    // グローバルの設定にプロファイルとリポジトリの設定を重ねた、実際に使う設定を読み込む
+    // （プロファイルの省略時は環境変数、選択中のプロファイルの順）
+    pub fn load_profile(profile: Option<&str>) -> Result<Self> {
+        let env_profile = std::env::var(PROFILE_ENV)
+            .ok()
+            .filter(|name| !name.is_empty());
+        let profile = profile.or(env_profile.as_deref());
+        let repo_config = get_repo_root().and_then(|root| find_repo_config(&root));
+        Self::load_layers(&get_config_path()?, profile, repo_config.as_deref())
+    }
+
+    // 既定値 < グローバル < プロファイル < リポジトリ の順に重ねる
+    pub fn load_layers(
+        global_path: &Path,
+        profile: Option<&str>,
+        repo_path: Option<&Path>,
+    ) -> Result<Self> {
+        let global = Self::load_from(global_path)?;
+        let profile = profile
+            .map(str::to_string)
+            .or_else(|| global.active_profile.clone());
+        if profile.is_none() && repo_path.is_none() {
+            return Ok(global);
+        }
+
+        let mut merged = serde_json::to_value(&global)?;
+        let mut sources = global.sources.clone();
 
+        if let Some(name) = profile {
+            let overlay = global.profiles.get(&name).cloned().ok_or_else(|| {
+                anyhow!(
+                    "Profile '{}' not found. Available profiles: {}",
+                    name,
+                    profile_names(&global)
+                )
+            })?;
+            let mut keys = Vec::new();
+            leaf_keys(&overlay, "", &mut keys);
+            merge(&mut merged, overlay);
+            config_from_value(merged.clone())
+                .with_context(|| format!("Invalid configuration in profile '{}'", name))?;
+            sources.profile = Some((name, keys));
+        }
+
+        if let Some(repo_path) = repo_path {
+            let overlay = read_repo_config(repo_path)?;
+            let mut keys = Vec::new();
+            leaf_keys(&overlay, "", &mut keys);
+            merge(&mut merged, overlay);
+            sources.repo = Some((repo_path.to_path_buf(), keys));
+        }
+
+        let mut config = config_from_value(merged).with_context(|| {
+            format!(
+                "Invalid configuration in {}",
+                repo_path
+                    .map(|p| p.display().to_string())
+                    .unwrap_or_default()
+            )
+        })?;
+        config.sources = sources;
+        Ok(config)
+    }
+
+    // 変更して保存するためのグローバルの設定を読み込む
+    pub fn load_global() -> Result<Self> {
+        Self::load_from(&get_config_path()?)
+    }
+
+    fn load_from(config_path: &Path) -> Result<Self> {
         if !config_path.exists() {
             return Ok(Config::new());
         }
 
-        let mut file = File::open(&config_path)?;
+        if let Some(warning) = permission_warning(config_path) {
+            eprintln!("{}", warning);
+        }
+
+        let mut file = File::open(config_path)?;
         let mut contents = String::new();
         file.read_to_string(&mut contents)?;
 
-        let config = serde_json::from_str(&contents).unwrap_or_else(|_| Config::new());
+        let mut config = parse_config(&contents, config_path)?;
+        if let Ok(value) = serde_json::from_str::<Value>(&contents) {
+            let mut keys = Vec::new();
+            leaf_keys(&value, "", &mut keys);
+            config.sources.global = Some((config_path.to_path_buf(), keys));
+        }
         Ok(config)
     }
 
     pub fn save(&self) -> Result<()> {
-        let config_path = get_config_path()?;
-
-        // Create config directory if it doesn't exist
-        if let Some(parent) = config_path.parent() {
-            fs::create_dir_all(parent)?;
-        }
+        self.save_to(&get_config_path()?)
+    }
 
+    pub fn save_to(&self, config_path: &Path) -> Result<()> {
         let json = serde_json::to_string_pretty(&self)?;
-        let mut file = OpenOptions::new()
-            .write(true)
-            .create(true)
-            .truncate(true)
-            .open(&config_path)?;
-
-        file.write_all(json.as_bytes())?;
-        Ok(())
+        write_private(config_path, json.as_bytes())
     }
 
-    pub fn get_api_key(&self) -> Result<String> {
+    // 指定したプラットフォームのAPIキーを取得する（フォールバック先にも使用）
+    pub fn api_key_for(&self, platform: Platform) -> Result<String> {
         // 環境変数から取得を試みる
-        if let Ok(key) = std::env::var(self.platform.env_var_name()) {
+        if let Ok(key) = std::env::var(platform.env_var_name()) {
             return Ok(key);
         }
 
         // 設定ファイルから取得
-        if let Some(key) = self.api_keys.get_key(self.platform) {
+        if let Some(key) = self.api_keys.get_key(platform) {
+            return Ok(key);
+        }
+
+        // APIキーが不要なプラットフォームでは空のキーを使う
+        //   キーリングや暗号化したファイルが読めなくても失敗しないよう、保存先は見ない
+        if !platform.requires_api_key() {
+            return Ok(String::new());
+        }
+
+        // キーリングまたは暗号化したファイルから取得
+        if self.key_storage != KeyStorage::Config
+            && let Some(key) = self.credential_store()?.get(platform)?
+        {
             return Ok(key);
         }
 
         // APIキーが見つからない場合はエラー
         Err(anyhow!(
             "{} is not set. Please set it with 'ai_commit_cli config --api'",
-            self.platform.env_var_name()
+            platform.env_var_name()
         ))
     }
 
+    // APIキーが環境変数、設定ファイル、キーリングのいずれかにあるか
+    pub fn has_api_key(&self, platform: Platform) -> bool {
+        std::env::var(platform.env_var_name()).is_ok()
+            || self.api_keys.get_key(platform).is_some()
+            || (self.key_storage != KeyStorage::Config
+                && self
+                    .credential_store()
+                    .and_then(|store| store.get(platform))
+                    .is_ok_and(|key| key.is_some()))
+    }
+
+    // APIキーをkey_storageの保存先に保存する（呼び出し元で設定ファイルも保存すること）
+    pub fn store_api_key(&mut self, platform: Platform, key: String) -> Result<()> {
+        if self.key_storage == KeyStorage::Config {
+            self.api_keys.set_key(platform, key);
+            return Ok(());
+        }
+
+        let location = self.credential_store()?.set(platform, &key)?;
+        self.api_keys.remove_key(platform);
+        println!("{} API key stored in {}.", platform.as_str(), location);
+        Ok(())
+    }
+
+    pub fn credential_store(&self) -> Result<CredentialStore> {
+        let config_path = get_config_path()?;
+        let dir = config_path
+            .parent()
+            .ok_or_else(|| anyhow!("Invalid configuration path"))?;
+        Ok(CredentialStore::new(self.key_storage, dir))
+    }
+
+    // 差分に使えるトークン数（0で無制限）
+    //   フォールバック先の上限が小さい場合は、フォールバックしたときに切り詰め直す
+    pub fn diff_token_budget(&self) -> usize {
+        self.diff_token_budget_for(api::provider_for(self.platform, self).as_ref())
+    }
+
+    pub fn diff_token_budget_for(&self, provider: &dyn api::Provider) -> usize {
+        self.diff
+            .max_tokens
+            .unwrap_or_else(|| provider.token_budget())
+    }
+
     pub fn get_model_name(&self) -> String {
-        self.selected_model
-            .clone()
-            .unwrap_or_else(|| self.platform.default_model_name().to_string())
+        self.model_name_for(self.platform)
+    }
+
+    // 指定したプラットフォームで使うモデル名を取得する
+    pub fn model_name_for(&self, platform: Platform) -> String {
+        // OpenAI互換エンドポイントは専用のモデル名を優先する
+        if platform == Platform::OpenAICompatible
+            && let Some(model) = &self.openai_compatible.model
+        {
+            return model.clone();
+        }
+
+        // Azureではデプロイメント名がモデルを表す
+        if platform == Platform::Azure && !self.azure.deployment.is_empty() {
+            return self.azure.deployment.clone();
+        }
+
+        // 選択済みのモデルはメインのプラットフォームにのみ適用する
+        if platform == self.platform
+            && let Some(model) = &self.selected_model
+        {
+            return model.clone();
+        }
+
+        platform.default_model_name().to_string()
     }
 }
 
+// 所有者だけが読み書きできるファイルとして書き込む
+//   一時ファイルに書いてから置き換えるため、途中で中断しても元のファイルは壊れない
+pub fn write_private(path: &Path, data: &[u8]) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        create_private_dir(parent)?;
+    }
+
+    // シンボリックリンク（dotfilesの管理ツールなど）はリンクのまま残し、リンク先を書き換える
+    let resolved;
+    let path = if path.is_symlink() {
+        resolved = fs::canonicalize(path)
+            .with_context(|| format!("Failed to resolve the symbolic link {}", path.display()))?;
+        resolved.as_path()
+    } else {
+        path
+    };
+
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| anyhow!("Invalid file path: {}", path.display()))?;
+    let temp_path = path.with_file_name(format!(
+        ".{}.{}.tmp",
+        file_name.to_string_lossy(),
+        std::process::id()
+    ));
+
+    let result = (|| -> Result<()> {
+        let mut options = OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let mut file = options.open(&temp_path)?;
+        file.write_all(data)?;
+        file.sync_all()?;
+        fs::rename(&temp_path, path)?;
+        Ok(())
+    })();
+
+    if result.is_err() {
+        fs::remove_file(&temp_path).ok();
+    }
+    result.with_context(|| format!("Failed to write {}", path.display()))
+}
+
+// 設定ディレクトリを作成し、所有者以外がアクセスできないようにする
+fn create_private_dir(dir: &Path) -> Result<()> {
+    fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(dir, fs::Permissions::from_mode(0o700))
+            .with_context(|| format!("Failed to set permissions on {}", dir.display()))?;
+    }
+    Ok(())
+}
+
+// 所有者以外も読み書きできる設定ファイルへの警告
+#[cfg(unix)]
+pub fn permission_warning(path: &Path) -> Option<String> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mode = fs::metadata(path).ok()?.permissions().mode() & 0o777;
+    if mode & 0o077 == 0 {
+        return None;
+    }
+    Some(format!(
+        "Warning: {} is accessible by other users (mode {:o}). Run `chmod 600 {}` to restrict it.",
+        path.display(),
+        mode,
+        path.display()
+    ))
+}
+
+#[cfg(not(unix))]
+pub fn permission_warning(_path: &Path) -> Option<String> {
+    None
+}
+
 pub fn get_config_path() -> Result<PathBuf> {
     let home = dirs::home_dir().ok_or_else(|| anyhow!("Could not find home directory"))?;
     Ok(home
@@ -191,12 +1035,28 @@ pub fn get_config_path() -> Result<PathBuf> {
 }
 
 // 対話式の選択機能 - プラットフォームとモデル
-pub fn select_platform_and_model() -> Result<(Platform, String)> {
+pub async fn select_platform_and_model(config: &mut Config) -> Result<(Platform, String)> {
     // まずプラットフォームを選択
     let platform = select_platform_only()?;
 
+    // OpenAI互換エンドポイントは接続先とモデル名を入力する
+    if platform == Platform::OpenAICompatible {
+        let settings = input_openai_compatible(&config.openai_compatible)?;
+        let model = settings.model.clone().unwrap_or_default();
+        config.openai_compatible = settings;
+        return Ok((platform, model));
+    }
+
+    // Azureはリソースとデプロイメントを入力する
+    if platform == Platform::Azure {
+        let settings = input_azure(&config.azure)?;
+        let deployment = settings.deployment.clone();
+        config.azure = settings;
+        return Ok((platform, deployment));
+    }
+
     // 次にモデルを選択
-    let model = select_model(platform)?;
+    let model = select_model(platform, &config.timeout).await?;
 
     Ok((platform, model))
 }
@@ -207,15 +1067,13 @@ fn select_platform_only() -> Result<Platform> {
     let mut theme = FancyTheme::default();
     let mut p = Promptuity::new(&mut term, &mut theme);
 
-    let options = vec![
-        ("Claude (Anthropic)", Platform::Claude),
-        ("OpenAI (GPT)", Platform::OpenAI),
-        ("Gemini (Google)", Platform::Gemini),
-    ];
+    let providers = api::providers();
 
-    let select_options: Vec<SelectOption<String>> = options
+    let select_options: Vec<SelectOption<String>> = providers
         .iter()
-        .map(|(label, _)| SelectOption::new(label.to_string(), label.to_string()))
+        .map(|provider| {
+            SelectOption::new(provider.label().to_string(), provider.label().to_string())
+        })
         .collect();
 
     let mut select = Select::new("Select AI platform for commit messages", select_options);
@@ -225,22 +1083,38 @@ fn select_platform_only() -> Result<Platform> {
     p.finish()?;
 
     // Find the matching platform based on the selected label
-    let selected_platform = options
+    let selected_platform = providers
         .iter()
-        .find(|(label, _)| label == &selected)
-        .map(|(_, platform)| *platform)
-        .unwrap_or(Platform::default());
+        .find(|provider| provider.label() == selected)
+        .map(|provider| provider.platform())
+        .unwrap_or_default();
 
     Ok(selected_platform)
 }
 
 // プラットフォームに対応するモデルを選択する関数
-pub fn select_model(platform: Platform) -> Result<String> {
+pub async fn select_model(platform: Platform, timeout: &TimeoutConfig) -> Result<String> {
+    // 利用可能なモデルを問い合わせ、取得できない場合は静的なリストを使う
+    let client = api::shared_client(timeout)?;
+    let models = match api::provider(platform).list_models(client).await {
+        Ok(models) if !models.is_empty() => models,
+        Ok(_) => {
+            eprintln!(
+                "No models found for {}. Showing default models.",
+                platform.as_str()
+            );
+            default_models(platform)
+        }
+        Err(e) => {
+            eprintln!("{}. Showing default models.", e);
+            default_models(platform)
+        }
+    };
+
     let mut term = Term::default();
     let mut theme = FancyTheme::default();
     let mut p = Promptuity::new(&mut term, &mut theme);
 
-    let models = platform.get_models();
     let select_options: Vec<SelectOption<String>> = models
         .iter()
         .map(|(label, id)| SelectOption::new(label.to_string(), id.to_string()))
@@ -258,8 +1132,109 @@ pub fn select_model(platform: Platform) -> Result<String> {
     Ok(selected_model_id)
 }
 
+fn default_models(platform: Platform) -> Vec<(String, String)> {
+    platform
+        .get_models()
+        .into_iter()
+        .map(|(label, id)| (label.to_string(), id.to_string()))
+        .collect()
+}
+
+// OpenAI互換エンドポイントの接続設定を対話的に入力する
+pub fn input_openai_compatible(current: &OpenAICompatibleConfig) -> Result<OpenAICompatibleConfig> {
+    let mut term = Term::default();
+    let mut theme = FancyTheme::default();
+    let mut p = Promptuity::new(&mut term, &mut theme);
+
+    let mut base_url = Input::new("Base URL (e.g. http://localhost:8000/v1)");
+    base_url.with_default(&current.base_url);
+    let mut model = Input::new("Model name");
+    model.with_default(current.model.clone().unwrap_or_default());
+    let mut auth_header = Input::new("Auth header name");
+    auth_header.with_default(&current.auth_header);
+    let mut auth_scheme = Input::new("Auth scheme (leave empty to send the key as-is)");
+    auth_scheme
+        .with_default(&current.auth_scheme)
+        .with_required(false);
+    let mut extra_headers = Input::new("Extra headers (e.g. X-Title: aic; X-Env: dev)");
+    extra_headers
+        .with_default(format_headers(&current.extra_headers))
+        .with_required(false);
+
+    p.begin()?;
+    let base_url = p.prompt(&mut base_url)?;
+    let model = p.prompt(&mut model)?;
+    let auth_header = p.prompt(&mut auth_header)?;
+    let auth_scheme = p.prompt(&mut auth_scheme)?;
+    let extra_headers = p.prompt(&mut extra_headers)?;
+    p.finish()?;
+
+    Ok(OpenAICompatibleConfig {
+        base_url: base_url.trim().to_string(),
+        auth_header: auth_header.trim().to_string(),
+        auth_scheme: auth_scheme.trim().to_string(),
+        extra_headers: parse_headers(&extra_headers)?,
+        model: Some(model.trim().to_string()),
+    })
+}
+
+// Azure OpenAIのデプロイメント設定を対話的に入力する
+pub fn input_azure(current: &AzureConfig) -> Result<AzureConfig> {
+    let mut term = Term::default();
+    let mut theme = FancyTheme::default();
+    let mut p = Promptuity::new(&mut term, &mut theme);
+
+    let mut resource = Input::new("Resource name or endpoint URL");
+    resource.with_default(&current.resource);
+    let mut deployment = Input::new("Deployment name");
+    deployment.with_default(&current.deployment);
+    let mut api_version = Input::new("API version");
+    api_version.with_default(&current.api_version);
+
+    p.begin()?;
+    let resource = p.prompt(&mut resource)?;
+    let deployment = p.prompt(&mut deployment)?;
+    let api_version = p.prompt(&mut api_version)?;
+    p.finish()?;
+
+    Ok(AzureConfig {
+        resource: resource.trim().to_string(),
+        deployment: deployment.trim().to_string(),
+        api_version: api_version.trim().to_string(),
+    })
+}
+
+// "Name: value; Name2: value2" 形式のヘッダー指定を解析する
+pub fn parse_headers(input: &str) -> Result<BTreeMap<String, String>> {
+    let mut headers = BTreeMap::new();
+
+    for entry in input.split(';').map(str::trim).filter(|e| !e.is_empty()) {
+        let (name, value) = entry
+            .split_once(':')
+            .ok_or_else(|| anyhow!("Invalid header '{}'. Use 'Name: value'", entry))?;
+        headers.insert(name.trim().to_string(), value.trim().to_string());
+    }
+
+    Ok(headers)
+}
+
+fn format_headers(headers: &BTreeMap<String, String>) -> String {
+    headers
+        .iter()
+        .map(|(name, value)| format!("{}: {}", name, value))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
 pub fn input_api_key(platform: Platform) -> Result<String> {
-    print!("Enter {} API key: ", platform.as_str());
+    if platform.requires_api_key() {
+        print!("Enter {} API key: ", platform.as_str());
+    } else {
+        print!(
+            "Enter {} API key (leave empty if not required): ",
+            platform.as_str()
+        );
+    }
     io::stdout().flush()?;
 
     let mut api_key = String::new();
@@ -268,17 +1243,51 @@ pub fn input_api_key(platform: Platform) -> Result<String> {
 }
 
 // 再帰を避けるために、引数からコピーして新しい関数として実装
-async fn do_config(api: bool, show: bool, language: bool, prompt: bool) -> Result<()> {
-    let mut config = Config::load()?;
+async fn do_config(
+    api: bool,
+    show: bool,
+    language: bool,
+    prompt: bool,
+    profile: Option<&str>,
+) -> Result<()> {
+    // --profileが指定された場合は、そのプロファイルの上書き値として保存する
+    let global = Config::load_global()?;
+    let mut config = match profile {
+        Some(name) => global.profile_config(name)?,
+        None => global.clone(),
+    };
 
     if show {
+        let config = Config::load_profile(profile)?;
+        // 値の後ろに読み込み元を表示する
+        let from = |key: &str| config.sources.source_of(key);
+        let model_key = match config.platform {
+            Platform::OpenAICompatible => "openai_compatible.model",
+            Platform::Azure => "azure.deployment",
+            _ => "selected_model",
+        };
+
         println!("Current configuration:");
-        println!("Language: {}", config.language.as_str());
-        println!("Platform: {}", config.platform.as_str());
-        println!("Model: {}", config.get_model_name());
+        if let Some(name) = config.sources.profile_name() {
+            println!("Profile: {}", name);
+        }
+        if let Some(path) = config.sources.repo_path() {
+            println!("Repository config: {}", path.display());
+        }
+        println!(
+            "Language: {} ({})",
+            config.language.as_str(),
+            from("language")
+        );
+        println!(
+            "Platform: {} ({})",
+            config.platform.as_str(),
+            from("platform")
+        );
+        println!("Model: {} ({})", config.get_model_name(), from(model_key));
         println!(
             "Claude API key: {}",
-            if config.api_keys.claude.is_some() {
+            if config.has_api_key(Platform::Claude) {
                 "Set"
             } else {
                 "Not set"
@@ -286,7 +1295,7 @@ async fn do_config(api: bool, show: bool, language: bool, prompt: bool) -> Resul
         );
         println!(
             "OpenAI API key: {}",
-            if config.api_keys.openai.is_some() {
+            if config.has_api_key(Platform::OpenAI) {
                 "Set"
             } else {
                 "Not set"
@@ -294,19 +1303,143 @@ async fn do_config(api: bool, show: bool, language: bool, prompt: bool) -> Resul
         );
         println!(
             "Gemini API key: {}",
-            if config.api_keys.gemini.is_some() {
+            if config.has_api_key(Platform::Gemini) {
                 "Set"
             } else {
                 "Not set"
             }
         );
+        if config.platform == Platform::OpenAICompatible {
+            let settings = &config.openai_compatible;
+            println!("OpenAI-compatible base URL: {}", settings.base_url);
+            println!(
+                "OpenAI-compatible auth: {} {}",
+                settings.auth_header,
+                if settings.auth_scheme.is_empty() {
+                    "<key>".to_string()
+                } else {
+                    format!("{} <key>", settings.auth_scheme)
+                }
+            );
+            if !settings.extra_headers.is_empty() {
+                println!(
+                    "OpenAI-compatible extra headers: {}",
+                    format_headers(&settings.extra_headers)
+                );
+            }
+            println!(
+                "OpenAI-compatible API key: {}",
+                if config.has_api_key(Platform::OpenAICompatible) {
+                    "Set"
+                } else {
+                    "Not set"
+                }
+            );
+        }
+        if config.platform == Platform::Azure {
+            println!("Azure endpoint: {}", config.azure.endpoint());
+            println!("Azure deployment: {}", config.azure.deployment);
+            println!("Azure API version: {}", config.azure.api_version);
+            println!(
+                "Azure API key: {}",
+                if config.has_api_key(Platform::Azure) {
+                    "Set"
+                } else {
+                    "Not set"
+                }
+            );
+        }
+        if !config.fallbacks.is_empty() {
+            let chain = config
+                .fallbacks
+                .iter()
+                .map(|fallback| {
+                    format!(
+                        "{} ({})",
+                        fallback.platform.as_str(),
+                        fallback
+                            .model
+                            .clone()
+                            .unwrap_or_else(|| config.model_name_for(fallback.platform))
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join(" -> ");
+            println!("Fallbacks: {} ({})", chain, from("fallbacks"));
+        }
+        match config.diff_token_budget() {
+            0 => println!("Diff token budget: Unlimited ({})", from("diff.max_tokens")),
+            budget => println!(
+                "Diff token budget: {} ({})",
+                budget,
+                from("diff.max_tokens")
+            ),
+        }
+        println!(
+            "Excluded files: {} ({})",
+            config.diff.exclude.join(", "),
+            from("diff.exclude")
+        );
+        println!(
+            "Secrets in the diff: {} ({})",
+            match config.secrets.action {
+                SecretAction::Redact => "Redact",
+                SecretAction::Abort => "Abort",
+            },
+            from("secrets.action")
+        );
         println!(
-            "Custom prompt: {}",
+            "Large diffs: {} ({})",
+            if config.diff.summarize {
+                "Summarize"
+            } else {
+                "Truncate"
+            },
+            from("diff.summarize")
+        );
+        println!(
+            "Commit format: {} ({})",
+            if config.conventional.enabled {
+                format!(
+                    "Conventional Commits (types: {}; scopes: {})",
+                    config.conventional.types.join(", "),
+                    if config.conventional.scopes.is_empty() {
+                        "any".to_string()
+                    } else {
+                        config.conventional.scopes.join(", ")
+                    }
+                )
+            } else {
+                "Free-form".to_string()
+            },
+            from("conventional.enabled")
+        );
+        if !config.conventional.path_scopes.is_empty() {
+            println!(
+                "Path scopes: {} ({})",
+                config
+                    .conventional
+                    .path_scopes
+                    .iter()
+                    .map(|(pattern, scope)| format!("{} -> {}", pattern, scope))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                from("conventional.path_scopes")
+            );
+        }
+        println!(
+            "Ollama API base: {}",
+            std::env::var(api::OLLAMA_API_BASE_ENV)
+                .unwrap_or_else(|_| "http://localhost:11434".to_string())
+        );
+        println!(
+            "Custom prompt: {} ({})",
             if config.custom_prompt.is_some() {
                 "Set"
             } else {
                 "Not set"
-            }
+            },
+            from("custom_prompt")
         );
 
         return Ok(());
@@ -314,38 +1447,45 @@ async fn do_config(api: bool, show: bool, language: bool, prompt: bool) -> Resul
 
     if api {
         // プラットフォームとモデルを選択
-        let (platform, model) = select_platform_and_model()?;
+        let (platform, model) = select_platform_and_model(&mut config).await?;
 
         // プラットフォームとモデルを設定に保存
         config.platform = platform;
         config.selected_model = Some(model);
 
-        // APIキーを入力
+        // APIキーを入力（不要なプラットフォームでは空欄を許可）
         let api_key = input_api_key(platform)?;
-        config.api_keys.set_key(platform, api_key);
+        if platform.requires_api_key() || !api_key.is_empty() {
+            config.store_api_key(platform, api_key)?;
+        }
 
         // 設定を保存
-        config.save()?;
+        save_for_profile(global, &config, profile)?;
         println!("API configuration and model saved successfully.");
         return Ok(());
     }
 
     if language {
         config.language = crate::language::select_language()?;
-        config.save()?;
+        save_for_profile(global.clone(), &config, profile)?;
         println!("Language set to: {}", config.language.as_str());
     }
 
     if prompt {
-        config.custom_prompt = Some(input_custom_prompt()?);
-        config.save()?;
-        println!("Custom prompt saved successfully.");
+        match input_custom_prompt()? {
+            Some(prompt) => {
+                config.custom_prompt = Some(prompt);
+                save_for_profile(global, &config, profile)?;
+                println!("Custom prompt saved successfully.");
+            }
+            None => println!("Custom prompt not changed."),
+        }
     }
 
     // 何も指定されていない場合は設定メニューを表示
     if !(api || show || language || prompt) {
         // 非同期再帰呼び出しをBoxでラップ
-        return Box::pin(do_config(true, false, false, false)).await;
+        return Box::pin(do_config(true, false, false, false, profile)).await;
     }
 
     Ok(())
@@ -356,11 +1496,526 @@ pub async fn handle_config_command(
     show: &bool,
     language: &bool,
     prompt: &bool,
+    profile: Option<&str>,
+) -> Result<()> {
+    do_config(*api, *show, *language, *prompt, profile).await
+}
+
+// `aic config set/get/unset` で扱う設定項目
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConfigKey {
+    Platform,
+    Model,
+    Language,
+    Prompt,
+}
+
+impl ConfigKey {
+    pub const ALL: [ConfigKey; 4] = [
+        ConfigKey::Platform,
+        ConfigKey::Model,
+        ConfigKey::Language,
+        ConfigKey::Prompt,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ConfigKey::Platform => "platform",
+            ConfigKey::Model => "model",
+            ConfigKey::Language => "language",
+            ConfigKey::Prompt => "prompt",
+        }
+    }
+
+    // メッセージに表示する名前
+    pub fn label(&self) -> &'static str {
+        match self {
+            ConfigKey::Platform => "Platform",
+            ConfigKey::Model => "Model",
+            ConfigKey::Language => "Language",
+            ConfigKey::Prompt => "Custom prompt",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Result<ConfigKey> {
+        ConfigKey::ALL
+            .into_iter()
+            .find(|key| key.as_str() == name.to_lowercase())
+            .ok_or_else(|| {
+                anyhow!(
+                    "Unknown configuration key: {}. Available keys: {}",
+                    name,
+                    ConfigKey::ALL
+                        .iter()
+                        .map(|key| key.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            })
+    }
+}
+
+impl Config {
+    // 設定項目の値を文字列で取得する（未設定のカスタムプロンプトはNone）
+    pub fn get_value(&self, key: ConfigKey) -> Option<String> {
+        match key {
+            ConfigKey::Platform => Some(self.platform.as_str().to_string()),
+            ConfigKey::Model => Some(self.get_model_name()),
+            ConfigKey::Language => Some(self.language.as_str().to_string()),
+            ConfigKey::Prompt => self.custom_prompt.clone(),
+        }
+    }
+
+    // プラットフォームを変更する（選択済みのモデルは前のプラットフォーム用なので既定に戻す）
+    pub fn set_platform(&mut self, platform: Platform) {
+        if platform != self.platform {
+            self.selected_model = None;
+        }
+        self.platform = platform;
+    }
+
+    // 現在のプラットフォームで使うモデルを設定する
+    pub fn set_model(&mut self, model: &str) {
+        match self.platform {
+            Platform::OpenAICompatible => self.openai_compatible.model = Some(model.to_string()),
+            Platform::Azure => self.azure.deployment = model.to_string(),
+            _ => self.selected_model = Some(model.to_string()),
+        }
+    }
+
+    // 設定項目を既定値に戻す
+    pub fn unset_value(&mut self, key: ConfigKey) {
+        match key {
+            ConfigKey::Platform => self.set_platform(Platform::default()),
+            ConfigKey::Model => {
+                self.selected_model = None;
+                match self.platform {
+                    Platform::OpenAICompatible => self.openai_compatible.model = None,
+                    Platform::Azure => self.azure.deployment.clear(),
+                    _ => {}
+                }
+            }
+            ConfigKey::Language => self.language = Language::default(),
+            ConfigKey::Prompt => self.custom_prompt = None,
+        }
+    }
+
+    // グローバルの設定にプロファイルだけを重ねた設定（リポジトリの設定は含まない）
+    pub fn profile_config(&self, name: &str) -> Result<Config> {
+        let overlay = self.profiles.get(name).cloned().ok_or_else(|| {
+            anyhow!(
+                "Profile '{}' not found. Available profiles: {}",
+                name,
+                profile_names(self)
+            )
+        })?;
+        let mut merged = serde_json::to_value(self)?;
+        merge(&mut merged, overlay);
+        config_from_value(merged)
+            .with_context(|| format!("Invalid configuration in profile '{}'", name))
+    }
+
+    // 変更したプロファイルの設定をプロファイルの上書き値として保存する
+    pub fn update_profile(&mut self, name: &str, profile: &Config) -> Result<()> {
+        let global = serde_json::to_value(&*self)?;
+        let previous = self.profiles.get(name).cloned().unwrap_or(Value::Null);
+        let overlay = profile_overlay(
+            &serde_json::to_value(profile)?,
+            Some(&global),
+            Some(&previous),
+        )
+        .unwrap_or_else(|| Value::Object(serde_json::Map::new()));
+        self.profiles.insert(name.to_string(), overlay);
+        Ok(())
+    }
+}
+
+// プロファイルの上書き値を作り直す
+//   元の上書き値にあるキーと、グローバルの設定と異なる値を残す
+fn profile_overlay(
+    updated: &Value,
+    global: Option<&Value>,
+    previous: Option<&Value>,
+) -> Option<Value> {
+    match updated {
+        Value::Object(map) if !map.is_empty() => {
+            let overlay: serde_json::Map<String, Value> = map
+                .iter()
+                .filter_map(|(name, child)| {
+                    profile_overlay(
+                        child,
+                        global.and_then(|g| g.get(name)),
+                        previous.and_then(|p| p.get(name)),
+                    )
+                    .map(|value| (name.clone(), value))
+                })
+                .collect();
+            (!overlay.is_empty()).then_some(Value::Object(overlay))
+        }
+        _ => (previous.is_some() || global != Some(updated)).then(|| updated.clone()),
+    }
+}
+
+// モデル名がプラットフォームのモデル一覧にあるか確認する
+//   OpenAI互換エンドポイントとAzureはモデル名やデプロイメント名を自由に指定できる
+//   一覧は新しいモデルの公開に追いつかないため、一覧にないモデルは警告だけにする
+pub async fn validate_model(
+    platform: Platform,
+    model: &str,
+    timeout: &TimeoutConfig,
+) -> Result<()> {
+    if matches!(platform, Platform::OpenAICompatible | Platform::Azure)
+        || model == platform.default_model_name()
+    {
+        return Ok(());
+    }
+
+    let client = api::shared_client(timeout)?;
+    let models = match api::provider(platform).list_models(client).await {
+        Ok(models) => models,
+        Err(e) => {
+            // 一覧を取得できない場合（Ollamaが起動していないなど）は確認せずに設定する
+            eprintln!("Could not verify the model: {}", e);
+            return Ok(());
+        }
+    };
+
+    if models.iter().any(|(_, id)| id == model) {
+        return Ok(());
+    }
+    let available = models
+        .iter()
+        .map(|(_, id)| id.as_str())
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    // Ollamaはインストール済みのモデルしか使えない
+    if platform == Platform::Ollama {
+        return Err(anyhow!(
+            "Unknown {} model: {}. Available models: {}",
+            platform.as_str(),
+            model,
+            available
+        ));
+    }
+    eprintln!(
+        "Warning: {} is not in the list of known {} models ({}). It will be used as is.",
+        model,
+        platform.as_str(),
+        available
+    );
+    Ok(())
+}
+
+// 設定項目を対話なしで変更する（カスタムプロンプトはファイルからも読み込める）
+//   プロファイルを指定した場合はそのプロファイルの値を変更する
+pub async fn set_value(
+    key: &str,
+    value: Option<&str>,
+    file: Option<&Path>,
+    profile: Option<&str>,
 ) -> Result<()> {
-    do_config(*api, *show, *language, *prompt).await
+    let key = ConfigKey::from_name(key)?;
+    let global = Config::load_global()?;
+    let mut config = match profile {
+        Some(name) => global.profile_config(name)?,
+        None => global.clone(),
+    };
+
+    let value = match (value, file) {
+        (Some(value), None) => value.to_string(),
+        (None, Some(path)) if key == ConfigKey::Prompt => {
+            if path == Path::new("-") {
+                let mut content = String::new();
+                io::stdin().read_to_string(&mut content)?;
+                content
+            } else {
+                fs::read_to_string(path)
+                    .with_context(|| format!("Failed to read {}", path.display()))?
+            }
+        }
+        (None, Some(_)) => return Err(anyhow!("--file can only be used with the prompt key")),
+        _ => return Err(anyhow!("Specify a value for {}", key.as_str())),
+    };
+
+    match key {
+        ConfigKey::Platform => {
+            let platform = Platform::from_name(&value)?;
+            let previous_model = config.get_model_name();
+            config.set_platform(platform);
+            if config.get_model_name() != previous_model {
+                println!(
+                    "Model set to the default for {}: {}",
+                    platform.as_str(),
+                    config.get_model_name()
+                );
+            }
+        }
+        ConfigKey::Model => {
+            validate_model(config.platform, &value, &config.timeout).await?;
+            config.set_model(&value);
+        }
+        ConfigKey::Language => config.language = Language::from_name(&value)?,
+        ConfigKey::Prompt => {
+            let prompt = value.trim_end().to_string();
+            if prompt.is_empty() {
+                return Err(anyhow!(
+                    "The custom prompt is empty. Use `aic config unset prompt` to remove it."
+                ));
+            }
+            config.custom_prompt = Some(prompt);
+        }
+    }
+
+    save_for_profile(global, &config, profile)?;
+    if key == ConfigKey::Prompt {
+        println!("Custom prompt saved successfully.");
+    } else {
+        println!(
+            "{} set to: {}",
+            key.label(),
+            config.get_value(key).unwrap_or_default()
+        );
+    }
+    Ok(())
 }
 
-pub fn input_custom_prompt() -> Result<String> {
+// 実際に使われる値（プロファイルとリポジトリの設定を反映したもの）を表示する
+pub fn get_value(key: &str, profile: Option<&str>) -> Result<()> {
+    let key = ConfigKey::from_name(key)?;
+    let config = Config::load_profile(profile)?;
+
+    if let Some(value) = config.get_value(key) {
+        println!("{}", value);
+    }
+    Ok(())
+}
+
+pub fn unset_value(key: &str, profile: Option<&str>) -> Result<()> {
+    let key = ConfigKey::from_name(key)?;
+    let global = Config::load_global()?;
+    let mut config = match profile {
+        Some(name) => global.profile_config(name)?,
+        None => global.clone(),
+    };
+
+    config.unset_value(key);
+    save_for_profile(global, &config, profile)?;
+
+    match config.get_value(key) {
+        Some(value) => println!("{} reset to: {}", key.label(), value),
+        None => println!("{} removed.", key.label()),
+    }
+    Ok(())
+}
+
+// 変更した設定を保存する（プロファイルの場合はグローバルの設定との差分をプロファイルに保存する）
+fn save_for_profile(mut global: Config, config: &Config, profile: Option<&str>) -> Result<()> {
+    match profile {
+        Some(name) => {
+            global.update_profile(name, config)?;
+            global.save()
+        }
+        None => config.save(),
+    }
+}
+
+// 設定ファイルに平文で保存されたAPIキーをキーリングまたは暗号化したファイルに移す
+pub fn migrate_keys() -> Result<()> {
+    let mut config = Config::load_global()?;
+
+    if config.key_storage == KeyStorage::Config {
+        return Err(anyhow!(
+            "key_storage is set to \"config\". Set it to \"auto\", \"keyring\" or \"file\" in {} to move the API keys out of the configuration file.",
+            get_config_path()?.display()
+        ));
+    }
+
+    let platforms: Vec<Platform> = Platform::ALL
+        .into_iter()
+        .filter(|platform| config.api_keys.get_key(*platform).is_some())
+        .collect();
+    if platforms.is_empty() {
+        println!("No API keys are stored in the configuration file.");
+        return Ok(());
+    }
+
+    for platform in platforms {
+        if let Some(key) = config.api_keys.get_key(platform) {
+            config.store_api_key(platform, key)?;
+        }
+    }
+    config.save()?;
+
+    println!("The configuration file no longer contains API keys.");
+    Ok(())
+}
+
+// プロファイルを対話的に作成する（同名のプロファイルは上書き）
+pub async fn add_profile(name: &str) -> Result<()> {
+    let mut config = Config::load_global()?;
+
+    // 接続先の入力欄にはグローバルの設定を初期値として表示する
+    let mut draft = config.clone();
+    let (platform, model) = select_platform_and_model(&mut draft).await?;
+    let language = crate::language::select_language()?;
+
+    let mut profile = serde_json::Map::new();
+    profile.insert("platform".to_string(), serde_json::to_value(platform)?);
+    profile.insert("language".to_string(), serde_json::to_value(language)?);
+    match platform {
+        Platform::OpenAICompatible => {
+            profile.insert(
+                "openai_compatible".to_string(),
+                serde_json::to_value(&draft.openai_compatible)?,
+            );
+        }
+        Platform::Azure => {
+            profile.insert("azure".to_string(), serde_json::to_value(&draft.azure)?);
+        }
+        _ => {
+            profile.insert("selected_model".to_string(), Value::String(model));
+        }
+    }
+
+    // APIキーはプロファイル間で共有し、未設定の場合のみ入力する
+    if platform.requires_api_key() && config.api_key_for(platform).is_err() {
+        let api_key = input_api_key(platform)?;
+        config.store_api_key(platform, api_key)?;
+    }
+
+    let replaced = config
+        .profiles
+        .insert(name.to_string(), Value::Object(profile))
+        .is_some();
+    config.save()?;
+
+    println!(
+        "Profile '{}' {}. Use it with `aic --profile {}` or `aic config profile use {}`.",
+        name,
+        if replaced { "updated" } else { "saved" },
+        name,
+        name
+    );
+    Ok(())
+}
+
+pub fn list_profiles() -> Result<()> {
+    let config = Config::load_global()?;
+
+    if config.profiles.is_empty() {
+        println!("No profiles. Create one with `aic config profile add <name>`.");
+        return Ok(());
+    }
+
+    for (name, profile) in &config.profiles {
+        let marker = if config.active_profile.as_deref() == Some(name) {
+            "*"
+        } else {
+            " "
+        };
+        let field = |key: &str| profile.get(key).and_then(Value::as_str).unwrap_or("-");
+        println!(
+            "{} {} ({}, {})",
+            marker,
+            name,
+            field("platform"),
+            field("language")
+        );
+    }
+    Ok(())
+}
+
+// 既定で使うプロファイルを選択する（名前を省略すると一覧から選ぶ）
+pub fn use_profile(name: Option<&str>) -> Result<()> {
+    let mut config = Config::load_global()?;
+
+    let name = match name {
+        Some(name) => Some(name.to_string()),
+        None => select_profile(&config, "Select the profile to use", true)?,
+    };
+
+    if let Some(name) = &name
+        && !config.profiles.contains_key(name)
+    {
+        return Err(anyhow!(
+            "Profile '{}' not found. Available profiles: {}",
+            name,
+            profile_names(&config)
+        ));
+    }
+
+    config.active_profile = name;
+    config.save()?;
+
+    match &config.active_profile {
+        Some(name) => println!("Using profile '{}'.", name),
+        None => println!("No profile is used."),
+    }
+    Ok(())
+}
+
+pub fn remove_profile(name: Option<&str>) -> Result<()> {
+    let mut config = Config::load_global()?;
+
+    let name = match name {
+        Some(name) => name.to_string(),
+        None => match select_profile(&config, "Select the profile to remove", false)? {
+            Some(name) => name,
+            None => return Ok(()),
+        },
+    };
+
+    if config.profiles.remove(&name).is_none() {
+        return Err(anyhow!(
+            "Profile '{}' not found. Available profiles: {}",
+            name,
+            profile_names(&config)
+        ));
+    }
+    if config.active_profile.as_deref() == Some(name.as_str()) {
+        config.active_profile = None;
+    }
+    config.save()?;
+
+    println!("Profile '{}' removed.", name);
+    Ok(())
+}
+
+// プロファイルを一覧から選ぶ（include_noneで「使用しない」を選択肢に加える）
+fn select_profile(config: &Config, message: &str, include_none: bool) -> Result<Option<String>> {
+    const NONE_LABEL: &str = "(no profile)";
+
+    if config.profiles.is_empty() {
+        return Err(anyhow!(
+            "No profiles. Create one with `aic config profile add <name>`."
+        ));
+    }
+
+    let mut term = Term::default();
+    let mut theme = FancyTheme::default();
+    let mut p = Promptuity::new(&mut term, &mut theme);
+
+    let mut select_options: Vec<SelectOption<String>> = config
+        .profiles
+        .keys()
+        .map(|name| SelectOption::new(name.clone(), name.clone()))
+        .collect();
+    if include_none {
+        select_options.push(SelectOption::new(NONE_LABEL, NONE_LABEL.to_string()));
+    }
+
+    let mut select = Select::new(message, select_options);
+
+    p.begin()?;
+    let selected = p.prompt(&mut select)?;
+    p.finish()?;
+
+    Ok(Some(selected).filter(|name| name != NONE_LABEL))
+}
+
+// 保存せずにエディタを閉じた場合はNone
+pub fn input_custom_prompt() -> Result<Option<String>> {
     println!("Custom prompt editor will open. Press Ctrl+S to save and Esc to exit.");
     println!("Write specific instructions for generating commit messages.");
     println!(
@@ -373,7 +2028,7 @@ pub fn input_custom_prompt() -> Result<String> {
     io::stdin().read_line(&mut input)?;
 
     // 現在のカスタムプロンプトを取得（存在する場合）
-    let config = Config::load()?;
+    let config = Config::load_global()?;
     let initial_content = config.custom_prompt.unwrap_or_default();
 
     // エディタを起動
@@ -381,7 +2036,5 @@ pub fn input_custom_prompt() -> Result<String> {
     let result = editor.run()?;
 
     // 末尾の改行を削除
-    let result = result.trim_end().to_string();
-
-    Ok(result)
+    Ok(result.map(|result| result.trim_end().to_string()))
 }
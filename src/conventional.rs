@@ -0,0 +1,306 @@
+use crate::config::ConventionalConfig;
+use crate::exclude;
+use anyhow::{Context, Result};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use regex::Regex;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::OnceLock;
+
+// commitlintの既定と同じ、1行目の最大文字数
+pub const MAX_HEADER_LENGTH: usize = 100;
+
+// モデルが使いがちな表記と、対応するtype
+const TYPE_ALIASES: [(&str, &str); 8] = [
+    ("feature", "feat"),
+    ("features", "feat"),
+    ("bugfix", "fix"),
+    ("bug", "fix"),
+    ("doc", "docs"),
+    ("documentation", "docs"),
+    ("tests", "test"),
+    ("refactoring", "refactor"),
+];
+
+// <type>(<scope>)!: <description>
+fn header_pattern() -> &'static Regex {
+    static HEADER: OnceLock<Regex> = OnceLock::new();
+    HEADER.get_or_init(|| {
+        Regex::new(r"^(?P<type>[a-z]+)(?:\((?P<scope>[^()\s]+)\))?(?P<breaking>!)?: (?P<description>\S.*)$")
+            .unwrap()
+    })
+}
+
+// 修正前の緩い1行目（大文字、空白、全角のコロンなど）
+fn loose_header_pattern() -> &'static Regex {
+    static LOOSE: OnceLock<Regex> = OnceLock::new();
+    LOOSE.get_or_init(|| {
+        Regex::new(
+            r"^\s*(?P<type>[A-Za-z]+)\s*(?:\(\s*(?P<scope>[^()]*?)\s*\))?\s*(?P<breaking>!)?\s*[:：]\s*(?P<description>\S.*)$",
+        )
+        .unwrap()
+    })
+}
+
+fn breaking_footer_pattern() -> &'static Regex {
+    static FOOTER: OnceLock<Regex> = OnceLock::new();
+    FOOTER.get_or_init(|| Regex::new(r"(?i)^breaking[ -]changes?\s*[:：]\s*").unwrap())
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Header<'a> {
+    pub kind: &'a str,
+    pub scope: Option<&'a str>,
+    pub breaking: bool,
+    pub description: &'a str,
+}
+
+pub fn parse_header(line: &str) -> Option<Header<'_>> {
+    let caps = header_pattern().captures(line)?;
+    Some(Header {
+        kind: caps.name("type")?.as_str(),
+        scope: caps.name("scope").map(|m| m.as_str()),
+        breaking: caps.name("breaking").is_some(),
+        description: caps.name("description")?.as_str(),
+    })
+}
+
+// システムプロンプトに付け加える形式の説明
+//   detectedはステージしたファイルのパスから決まったscope（以下同じ）
+pub fn instructions(config: &ConventionalConfig, detected: &[String]) -> String {
+    let scopes = if let [scope] = detected {
+        format!("The scope must be `{}`.", scope)
+    } else if !detected.is_empty() {
+        format!(
+            "The changes span several scopes ({}). Use the scope of the main change, or omit the scope.",
+            detected.join(", ")
+        )
+    } else if config.scopes.is_empty() {
+        "Add a scope in parentheses when the change is limited to one area, for example `feat(parser): ...`.".to_string()
+    } else {
+        format!(
+            "The scope is optional. If you add one, it must be one of: {}.",
+            config.scopes.join(", ")
+        )
+    };
+
+    format!(
+        "This overrides the format above. Write the commit message in the Conventional Commits 1.0.0 format:
+```
+<type>(<scope>)!: <description>
+
+<body>
+
+BREAKING CHANGE: <description of the breaking change>
+```
+- The type must be one of: {}.
+- {}
+- Add `!` after the type or scope and a `BREAKING CHANGE:` footer only if the change breaks backward compatibility.
+- Keep the first line under {} characters. Separate the body and footers with a blank line.
+- Output only the commit message, without quotes or code fences.",
+        config.types.join(", "),
+        scopes,
+        MAX_HEADER_LENGTH
+    )
+}
+
+// 形式に合わない点を返す（空なら正しい）
+pub fn validate(message: &str, config: &ConventionalConfig, detected: &[String]) -> Vec<String> {
+    let mut problems = Vec::new();
+    let mut lines = message.lines();
+
+    let Some(first) = lines.next().filter(|line| !line.trim().is_empty()) else {
+        return vec!["The message is empty".to_string()];
+    };
+
+    match parse_header(first) {
+        None => problems.push(
+            "The first line must be `<type>(<scope>): <description>` or `<type>: <description>`"
+                .to_string(),
+        ),
+        Some(header) => {
+            if !config.types.iter().any(|t| t == header.kind) {
+                problems.push(format!(
+                    "The type `{}` is not allowed. Use one of: {}",
+                    header.kind,
+                    config.types.join(", ")
+                ));
+            }
+            if let Some(problem) = scope_problem(header.scope, config, detected) {
+                problems.push(problem);
+            }
+        }
+    }
+
+    if first.chars().count() > MAX_HEADER_LENGTH {
+        problems.push(format!(
+            "The first line is longer than {} characters",
+            MAX_HEADER_LENGTH
+        ));
+    }
+
+    if lines.next().is_some_and(|line| !line.trim().is_empty()) {
+        problems.push("Separate the body from the first line with a blank line".to_string());
+    }
+
+    let malformed_footer = message.lines().skip(1).any(|line| {
+        breaking_footer_pattern().is_match(line)
+            && !line.starts_with("BREAKING CHANGE: ")
+            && !line.starts_with("BREAKING-CHANGE: ")
+    });
+    if malformed_footer {
+        problems.push(
+            "Write the breaking change footer as `BREAKING CHANGE: <description>`".to_string(),
+        );
+    }
+
+    problems
+}
+
+// scopeが設定やステージしたファイルのパスに合っているか
+fn scope_problem(
+    scope: Option<&str>,
+    config: &ConventionalConfig,
+    detected: &[String],
+) -> Option<String> {
+    // パスから決まったscopeは、設定のscopeの一覧より優先する
+    let allowed = if detected.is_empty() {
+        config.scopes.as_slice()
+    } else {
+        detected
+    };
+
+    match (scope, detected) {
+        (None, [required]) => Some(format!("The scope must be `{}`", required)),
+        (Some(scope), [required]) if scope != required => Some(format!(
+            "The scope `{}` does not match the changed files. Use `{}`",
+            scope, required
+        )),
+        (Some(scope), _) if !allowed.is_empty() && !allowed.iter().any(|s| s == scope) => {
+            Some(format!(
+                "The scope `{}` is not allowed. Use one of: {}",
+                scope,
+                allowed.join(", ")
+            ))
+        }
+        _ => None,
+    }
+}
+
+// 機械的に直せる違反を直す（コードブロック、typeの大文字や別名、省略したscope、区切りの空行、フッターの表記）
+pub fn repair(message: &str, config: &ConventionalConfig, detected: &[String]) -> String {
+    let mut lines: Vec<String> = message
+        .trim()
+        .lines()
+        .filter(|line| !line.trim_start().starts_with("```"))
+        .map(|line| line.trim_end().to_string())
+        .collect();
+
+    // 前後の空行を除く
+    while lines.first().is_some_and(|line| line.trim().is_empty()) {
+        lines.remove(0);
+    }
+    while lines.last().is_some_and(|line| line.trim().is_empty()) {
+        lines.pop();
+    }
+    let Some(first) = lines.first_mut() else {
+        return String::new();
+    };
+
+    let trimmed = first
+        .trim()
+        .trim_matches(|c| c == '`' || c == '"')
+        .to_string();
+    if let Some(caps) = loose_header_pattern().captures(&trimmed) {
+        let kind = caps["type"].to_lowercase();
+        let kind = TYPE_ALIASES
+            .iter()
+            .find(|(alias, _)| *alias == kind && !config.types.contains(&kind))
+            .map(|(_, canonical)| canonical.to_string())
+            .unwrap_or(kind);
+        let scope = caps
+            .name("scope")
+            .map(|m| m.as_str().replace(' ', "-"))
+            .filter(|scope| !scope.is_empty())
+            // パスから1つに決まるscopeは省略されていれば補う
+            .or_else(|| match detected {
+                [required] => Some(required.clone()),
+                _ => None,
+            })
+            .map(|scope| format!("({})", scope))
+            .unwrap_or_default();
+        let breaking = if caps.name("breaking").is_some() {
+            "!"
+        } else {
+            ""
+        };
+        *first = format!("{}{}{}: {}", kind, scope, breaking, &caps["description"]);
+    }
+
+    if lines.len() > 1 && !lines[1].trim().is_empty() {
+        lines.insert(1, String::new());
+    }
+
+    for line in lines.iter_mut().skip(1) {
+        if breaking_footer_pattern().is_match(line) && !line.starts_with("BREAKING-CHANGE: ") {
+            *line = breaking_footer_pattern()
+                .replace(line, "BREAKING CHANGE: ")
+                .into_owned();
+        }
+    }
+
+    lines.join("\n")
+}
+
+// 生成し直すときにモデルへ伝える指示
+pub fn fix_instructions(problems: &[String]) -> String {
+    format!(
+        "The message must follow the Conventional Commits format. Fix these problems: {}.",
+        problems.join("; ")
+    )
+}
+
+// パスのパターンとscopeの対応
+//   パターンは .aicignore と同じ形式で、複数に一致する場合は最も長いパターンを使う
+pub struct ScopeMap {
+    set: GlobSet,
+    // パターンごとの (パターンの長さ, scope)
+    scopes: Vec<(usize, String)>,
+}
+
+impl ScopeMap {
+    pub fn new(path_scopes: &BTreeMap<String, String>) -> Result<Self> {
+        let mut builder = GlobSetBuilder::new();
+        let mut scopes = Vec::new();
+
+        for (pattern, scope) in path_scopes {
+            let glob = Glob::new(&exclude::normalize(pattern.trim()))
+                .with_context(|| format!("Invalid path_scopes pattern: {}", pattern))?;
+            builder.add(glob);
+            scopes.push((pattern.trim().len(), scope.clone()));
+        }
+
+        Ok(Self {
+            set: builder.build()?,
+            scopes,
+        })
+    }
+
+    pub fn scope_for(&self, path: &str) -> Option<&str> {
+        self.set
+            .matches(path)
+            .into_iter()
+            .max_by_key(|&index| self.scopes[index].0)
+            .map(|index| self.scopes[index].1.as_str())
+    }
+
+    // ファイルのパスに対応するscopeの一覧（どのパターンにも一致しないファイルは無視する）
+    pub fn scopes_for<S: AsRef<str>>(&self, paths: &[S]) -> Vec<String> {
+        paths
+            .iter()
+            .filter_map(|path| self.scope_for(path.as_ref()))
+            .map(str::to_string)
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect()
+    }
+}
@@ -0,0 +1,279 @@
+use crate::config::{KeyStorage, Platform, write_private};
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use anyhow::{Context, Result, anyhow};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// キーリングに登録するサービス名
+#[cfg_attr(test, allow(dead_code))]
+pub const SERVICE: &str = "ai_commit_cli";
+
+// 暗号化したAPIキーと、その暗号鍵のファイル名（設定ファイルと同じディレクトリに置く）
+pub const CREDENTIALS_FILE: &str = "credentials.enc";
+pub const CREDENTIALS_KEY_FILE: &str = "credentials.key";
+
+// AES-GCMのナンスの長さ
+const NONCE_LEN: usize = 12;
+
+// キーリングのアカウント名（設定ファイルの api_keys の項目名と同じ）
+fn account(platform: Platform) -> &'static str {
+    platform.key_name()
+}
+
+// APIキーの保存先
+//   Keyring: macOSのキーチェーン、WindowsのCredential Manager、LinuxのSecret Service
+//   File: ランダムな鍵で暗号化したファイル（キーリングが使えない環境向け）
+pub struct CredentialStore {
+    storage: KeyStorage,
+    dir: PathBuf,
+    keyring: Box<dyn Keyring>,
+}
+
+impl CredentialStore {
+    pub fn new(storage: KeyStorage, dir: &Path) -> Self {
+        // テストでは実際のキーリングに触れないよう、使えないキーリングとして扱う
+        #[cfg(not(test))]
+        let keyring: Box<dyn Keyring> = Box::new(OsKeyring);
+        #[cfg(test)]
+        let keyring: Box<dyn Keyring> = Box::new(MemoryKeyring::unavailable());
+
+        Self {
+            storage,
+            dir: dir.to_path_buf(),
+            keyring,
+        }
+    }
+
+    // キーリングを差し替える（テスト用）
+    #[cfg(test)]
+    pub fn with_keyring(storage: KeyStorage, dir: &Path, keyring: impl Keyring + 'static) -> Self {
+        Self {
+            storage,
+            dir: dir.to_path_buf(),
+            keyring: Box::new(keyring),
+        }
+    }
+
+    // キーリングを使うかどうか
+    fn uses_keyring(&self) -> bool {
+        matches!(self.storage, KeyStorage::Auto | KeyStorage::Keyring)
+    }
+
+    fn uses_file(&self) -> bool {
+        matches!(self.storage, KeyStorage::Auto | KeyStorage::File)
+    }
+
+    pub fn get(&self, platform: Platform) -> Result<Option<String>> {
+        if self.uses_keyring() {
+            match self.keyring.get(account(platform)) {
+                Ok(Some(key)) => return Ok(Some(key)),
+                Ok(None) => {}
+                Err(e) if self.storage == KeyStorage::Keyring => return Err(e),
+                Err(_) => {}
+            }
+        }
+        if self.uses_file() {
+            return Ok(self.encrypted_file().read()?.remove(account(platform)));
+        }
+        Ok(None)
+    }
+
+    // APIキーを保存し、保存先の説明を返す
+    pub fn set(&self, platform: Platform, key: &str) -> Result<String> {
+        if self.uses_keyring() {
+            match self.keyring.set(account(platform), key) {
+                Ok(()) => {
+                    // 以前にファイルへ保存したキーは残さない
+                    if self.uses_file() {
+                        self.encrypted_file().remove(account(platform))?;
+                    }
+                    return Ok("the OS keyring".to_string());
+                }
+                Err(e) if self.storage == KeyStorage::Keyring => {
+                    return Err(e.context("Failed to store the API key in the OS keyring"));
+                }
+                Err(e) => eprintln!(
+                    "The OS keyring is not available ({}). Storing the API key in an encrypted file instead.",
+                    e
+                ),
+            }
+        }
+        if self.uses_file() {
+            let file = self.encrypted_file();
+            file.insert(account(platform), key)?;
+            return Ok(file.path.display().to_string());
+        }
+        Err(anyhow!(
+            "API keys are stored in the configuration file (key_storage = \"config\")"
+        ))
+    }
+
+    fn encrypted_file(&self) -> EncryptedFile {
+        EncryptedFile::new(&self.dir)
+    }
+}
+
+// キーリングの読み書き
+pub trait Keyring {
+    fn get(&self, account: &str) -> Result<Option<String>>;
+    fn set(&self, account: &str, key: &str) -> Result<()>;
+}
+
+// OSのキーリング
+#[cfg_attr(test, allow(dead_code))]
+pub struct OsKeyring;
+
+impl Keyring for OsKeyring {
+    fn get(&self, account: &str) -> Result<Option<String>> {
+        match keyring::Entry::new(SERVICE, account)?.get_password() {
+            Ok(key) => Ok(Some(key)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn set(&self, account: &str, key: &str) -> Result<()> {
+        Ok(keyring::Entry::new(SERVICE, account)?.set_password(key)?)
+    }
+}
+
+// メモリ上のキーリング（テスト用）
+#[cfg(test)]
+pub struct MemoryKeyring {
+    keys: std::sync::Mutex<BTreeMap<String, String>>,
+    available: bool,
+}
+
+#[cfg(test)]
+impl MemoryKeyring {
+    pub fn new() -> Self {
+        Self {
+            keys: Default::default(),
+            available: true,
+        }
+    }
+
+    // キーリングが無い環境と同じく、読み書きに失敗する
+    pub fn unavailable() -> Self {
+        Self {
+            keys: Default::default(),
+            available: false,
+        }
+    }
+
+    fn check(&self) -> Result<()> {
+        if self.available {
+            Ok(())
+        } else {
+            Err(anyhow!("No keyring is available"))
+        }
+    }
+}
+
+#[cfg(test)]
+impl Keyring for MemoryKeyring {
+    fn get(&self, account: &str) -> Result<Option<String>> {
+        self.check()?;
+        Ok(self.keys.lock().unwrap().get(account).cloned())
+    }
+
+    fn set(&self, account: &str, key: &str) -> Result<()> {
+        self.check()?;
+        self.keys
+            .lock()
+            .unwrap()
+            .insert(account.to_string(), key.to_string());
+        Ok(())
+    }
+}
+
+// AES-256-GCMで暗号化したAPIキーのファイル
+//   中身はナンスと、{アカウント名: APIキー} のJSONを暗号化したもの
+//   鍵は別のファイルに保存するため、設定ファイルやこのファイルだけが漏れてもキーは読めない
+pub struct EncryptedFile {
+    path: PathBuf,
+    key_path: PathBuf,
+}
+
+impl EncryptedFile {
+    pub fn new(dir: &Path) -> Self {
+        Self {
+            path: dir.join(CREDENTIALS_FILE),
+            key_path: dir.join(CREDENTIALS_KEY_FILE),
+        }
+    }
+
+    pub fn read(&self) -> Result<BTreeMap<String, String>> {
+        if !self.path.exists() {
+            return Ok(BTreeMap::new());
+        }
+
+        let data = fs::read(&self.path)
+            .with_context(|| format!("Failed to read {}", self.path.display()))?;
+        if data.len() < NONCE_LEN {
+            return Err(anyhow!("{} is corrupted", self.path.display()));
+        }
+        let (nonce, ciphertext) = data.split_at(NONCE_LEN);
+        let plaintext = self
+            .cipher(false)?
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| {
+                anyhow!(
+                    "Failed to decrypt {}. The key file {} may have been changed.",
+                    self.path.display(),
+                    self.key_path.display()
+                )
+            })?;
+        Ok(serde_json::from_slice(&plaintext)?)
+    }
+
+    pub fn insert(&self, account: &str, key: &str) -> Result<()> {
+        let mut keys = self.read()?;
+        keys.insert(account.to_string(), key.to_string());
+        self.write(&keys)
+    }
+
+    pub fn remove(&self, account: &str) -> Result<()> {
+        let mut keys = self.read()?;
+        if keys.remove(account).is_some() {
+            self.write(&keys)?;
+        }
+        Ok(())
+    }
+
+    fn write(&self, keys: &BTreeMap<String, String>) -> Result<()> {
+        let cipher = self.cipher(true)?;
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = cipher
+            .encrypt(&nonce, serde_json::to_vec(keys)?.as_slice())
+            .map_err(|_| anyhow!("Failed to encrypt the API keys"))?;
+
+        let mut data = nonce.to_vec();
+        data.extend(ciphertext);
+        write_private(&self.path, &data)
+    }
+
+    // 鍵ファイルを読み込む（createで存在しない場合は作成する）
+    fn cipher(&self, create: bool) -> Result<Aes256Gcm> {
+        if !self.key_path.exists() {
+            if !create {
+                return Err(anyhow!(
+                    "The key file {} for {} is missing",
+                    self.key_path.display(),
+                    self.path.display()
+                ));
+            }
+            let key = Aes256Gcm::generate_key(&mut OsRng);
+            write_private(&self.key_path, &key)?;
+        }
+
+        let key = fs::read(&self.key_path)
+            .with_context(|| format!("Failed to read {}", self.key_path.display()))?;
+        if key.len() != 32 {
+            return Err(anyhow!("{} is corrupted", self.key_path.display()));
+        }
+        Ok(Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key)))
+    }
+}
@@ -0,0 +1,583 @@
+// ステージされた差分の解析と、トークン数の上限に合わせた切り詰め・分割
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FileStatus {
+    Added,
+    Deleted,
+    Renamed,
+    Modified,
+}
+
+impl FileStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FileStatus::Added => "added",
+            FileStatus::Deleted => "deleted",
+            FileStatus::Renamed => "renamed",
+            FileStatus::Modified => "modified",
+        }
+    }
+}
+
+// 自動生成されたファイルの先頭に書かれる目印
+const GENERATED_MARKERS: [&str; 2] = ["@generated", "DO NOT EDIT"];
+
+// 目印を探す先頭からの行数
+const GENERATED_MARKER_LINES: usize = 10;
+
+// 先頭のコメントとみなす行の書き出し
+const COMMENT_PREFIXES: [&str; 9] = ["//", "/*", "*", "#", "--", ";", "%", "<!--", "\"\"\""];
+
+// UTF-8として読めなかったファイルに付ける目印
+pub const NON_UTF8_MARKER: &str = "(non-UTF-8 content, invalid bytes replaced with U+FFFD)";
+
+// 1ファイル分の差分
+#[derive(Debug, Clone)]
+pub struct FileDiff {
+    pub path: String,
+    pub status: FileStatus,
+    // "diff --git" から最初のハンクまでの行
+    header: Vec<String>,
+    hunks: Vec<Hunk>,
+}
+
+#[derive(Debug, Clone)]
+struct Hunk {
+    // "@@ -1,3 +1,4 @@" の行
+    header: String,
+    lines: Vec<String>,
+    // 切り詰めで省略した行数
+    omitted: usize,
+}
+
+impl FileDiff {
+    pub fn additions(&self) -> usize {
+        self.count_lines('+')
+    }
+
+    pub fn deletions(&self) -> usize {
+        self.count_lines('-')
+    }
+
+    fn count_lines(&self, prefix: char) -> usize {
+        self.hunks
+            .iter()
+            .flat_map(|hunk| &hunk.lines)
+            .filter(|line| line.starts_with(prefix))
+            .count()
+    }
+
+    // "modified Cargo.lock (+120/-80)" の形式の要約
+    pub fn summary(&self) -> String {
+        if self.is_binary() {
+            return format!("{} {} (binary)", self.status.as_str(), self.path);
+        }
+
+        format!(
+            "{} {} (+{}/-{}{})",
+            self.status.as_str(),
+            self.path,
+            self.additions(),
+            self.deletions(),
+            if self.is_generated() {
+                ", generated"
+            } else {
+                ""
+            }
+        )
+    }
+
+    // バイナリファイルの差分かどうか
+    pub fn is_binary(&self) -> bool {
+        self.header
+            .iter()
+            .any(|line| line.starts_with("Binary files ") || line == "GIT binary patch")
+    }
+
+    // ファイルの先頭のコメントに自動生成を示す目印があるかどうか
+    //   途中の変更に目印の文字列が含まれているだけのファイルは対象外
+    pub fn is_generated(&self) -> bool {
+        let Some(hunk) = self.hunks.first() else {
+            return false;
+        };
+
+        // 削除されたファイルは削除前の内容を調べる
+        let (old_start, new_start) = hunk_starts(&hunk.header);
+        let (start, other_side) = if self.status == FileStatus::Deleted {
+            (old_start, '+')
+        } else {
+            (new_start, '-')
+        };
+        if start != 1 {
+            return false;
+        }
+
+        hunk.lines
+            .iter()
+            .filter(|line| !line.starts_with(other_side))
+            .map(|line| line.get(1..).unwrap_or_default().trim())
+            .take(GENERATED_MARKER_LINES)
+            .take_while(|line| {
+                line.is_empty()
+                    || COMMENT_PREFIXES
+                        .iter()
+                        .any(|prefix| line.starts_with(prefix))
+            })
+            .any(|line| GENERATED_MARKERS.iter().any(|marker| line.contains(marker)))
+    }
+
+    // 差分全体を文字列に戻す
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        for line in &self.header {
+            push_line(&mut out, line);
+        }
+        for hunk in &self.hunks {
+            push_line(&mut out, &hunk.header);
+            for line in &hunk.lines {
+                push_line(&mut out, line);
+            }
+            if hunk.omitted > 0 {
+                push_line(&mut out, &omitted_marker(hunk.omitted));
+            }
+        }
+        out
+    }
+
+    // ファイルヘッダーとハンクヘッダーだけを残す
+    fn render_headers(&self) -> String {
+        let mut out = String::new();
+        for line in &self.header {
+            push_line(&mut out, line);
+        }
+        for hunk in &self.hunks {
+            push_line(&mut out, &hunk.header);
+            push_line(&mut out, &omitted_marker(hunk.lines.len() + hunk.omitted));
+        }
+        out
+    }
+
+    // トークン数の上限ごとに分割する（各部分にファイルヘッダーを付ける）
+    fn split(&self, max_tokens: usize, estimate: &impl Fn(&str) -> usize) -> Vec<String> {
+        let mut header = String::new();
+        for line in &self.header {
+            push_line(&mut header, line);
+        }
+        let header_cost = estimate(&header);
+
+        let mut pieces = Vec::new();
+        let mut current = header.clone();
+        let mut cost = header_cost;
+        for hunk in &self.hunks {
+            for part in hunk.split(max_tokens.saturating_sub(header_cost), estimate) {
+                let part_cost = estimate(&part);
+                if current.len() > header.len() && cost + part_cost > max_tokens {
+                    pieces.push(std::mem::replace(&mut current, header.clone()));
+                    cost = header_cost;
+                }
+                current.push_str(&part);
+                cost += part_cost;
+            }
+        }
+        pieces.push(current);
+        pieces
+    }
+
+    // 長すぎるハンクを切り詰め、切り詰めたハンクの数を返す
+    fn truncate_hunks(&mut self, max_lines: usize) -> usize {
+        let mut truncated = 0;
+        for hunk in &mut self.hunks {
+            if hunk.lines.len() > max_lines {
+                hunk.omitted += hunk.lines.len() - max_lines;
+                hunk.lines.truncate(max_lines);
+                truncated += 1;
+            }
+        }
+        truncated
+    }
+}
+
+impl Hunk {
+    // トークン数の上限ごとに分割する（続きの部分にもハンクヘッダーを付ける）
+    fn split(&self, max_tokens: usize, estimate: &impl Fn(&str) -> usize) -> Vec<String> {
+        let mut header = String::new();
+        push_line(&mut header, &self.header);
+        let header_cost = estimate(&header);
+
+        let mut parts = Vec::new();
+        let mut current = header.clone();
+        let mut cost = header_cost;
+        for line in &self.lines {
+            let line_cost = estimate(line) + 1;
+            if current.len() > header.len() && cost + line_cost > max_tokens {
+                parts.push(std::mem::replace(&mut current, header.clone()));
+                cost = header_cost;
+            }
+            push_line(&mut current, line);
+            cost += line_cost;
+        }
+        parts.push(current);
+        parts
+    }
+}
+
+fn push_line(out: &mut String, line: &str) {
+    out.push_str(line);
+    out.push('\n');
+}
+
+fn omitted_marker(lines: usize) -> String {
+    format!("... ({} lines omitted)", lines)
+}
+
+// `git diff` の生の出力を文字列にする（UTF-8でないファイルは置換文字で表し、目印を付ける）
+pub fn decode(raw: &[u8]) -> String {
+    let mut starts: Vec<usize> = (0..raw.len())
+        .filter(|&i| (i == 0 || raw[i - 1] == b'\n') && raw[i..].starts_with(b"diff --git "))
+        .collect();
+    if starts.first() != Some(&0) {
+        starts.insert(0, 0);
+    }
+    starts.push(raw.len());
+
+    let mut out = String::new();
+    for range in starts.windows(2) {
+        let segment = &raw[range[0]..range[1]];
+        match std::str::from_utf8(segment) {
+            Ok(text) => out.push_str(text),
+            Err(_) => {
+                let text = String::from_utf8_lossy(segment);
+                match text.find('\n') {
+                    Some(pos) => {
+                        out.push_str(&text[..=pos]);
+                        push_line(&mut out, NON_UTF8_MARKER);
+                        out.push_str(&text[pos + 1..]);
+                    }
+                    None => out.push_str(&text),
+                }
+            }
+        }
+    }
+    out
+}
+
+// `git diff` の出力をファイルごとに分割する
+pub fn parse(diff: &str) -> Vec<FileDiff> {
+    let mut files: Vec<FileDiff> = Vec::new();
+
+    for line in diff.lines() {
+        if let Some(paths) = line.strip_prefix("diff --git ") {
+            files.push(FileDiff {
+                path: path_from_diff_line(paths),
+                status: FileStatus::Modified,
+                header: vec![line.to_string()],
+                hunks: Vec::new(),
+            });
+            continue;
+        }
+
+        // 最初のファイルより前の行は無視する
+        let Some(file) = files.last_mut() else {
+            continue;
+        };
+
+        if line.starts_with("@@") {
+            file.hunks.push(Hunk {
+                header: line.to_string(),
+                lines: Vec::new(),
+                omitted: 0,
+            });
+        } else if let Some(hunk) = file.hunks.last_mut() {
+            hunk.lines.push(line.to_string());
+        } else {
+            if line.starts_with("new file mode") {
+                file.status = FileStatus::Added;
+            } else if line.starts_with("deleted file mode") {
+                file.status = FileStatus::Deleted;
+            } else if let Some(path) = line.strip_prefix("rename to ") {
+                file.status = FileStatus::Renamed;
+                file.path = header_path(path);
+            } else if let Some(path) = line
+                .strip_prefix("+++ ")
+                .map(header_path)
+                .and_then(|path| path.strip_prefix("b/").map(str::to_string))
+            {
+                file.path = path;
+            }
+            file.header.push(line.to_string());
+        }
+    }
+
+    files
+}
+
+// "@@ -12,3 +14,5 @@" から旧・新ファイルの開始行を取り出す
+pub fn hunk_starts(line: &str) -> (usize, usize) {
+    let mut ranges = line.split_whitespace().skip(1);
+    let start = |range: Option<&str>| {
+        range
+            .and_then(|r| r.get(1..))
+            .and_then(|r| r.split(',').next())
+            .and_then(|n| n.parse().ok())
+            .unwrap_or(0)
+    };
+    let old = start(ranges.next());
+    let new = start(ranges.next());
+    (old, new)
+}
+
+// "a/src/main.rs b/src/main.rs" から変更後のパスを取り出す
+//   特殊な文字を含むパスは "a/..." "b/..." のように引用符で囲まれる
+pub fn path_from_diff_line(paths: &str) -> String {
+    let paths = paths.trim_end();
+    if paths.ends_with('"')
+        && let Some(pos) = paths.rfind(" \"b/")
+    {
+        let path = unquote_path(&paths[pos + 1..]);
+        return path.strip_prefix("b/").unwrap_or(&path).to_string();
+    }
+    match paths.rfind(" b/") {
+        Some(pos) => paths[pos + 3..].to_string(),
+        None => paths.to_string(),
+    }
+}
+
+// "+++ b/path" や "rename to path" の行のパス
+//   空白を含むパスの後ろに付くタブは除く
+fn header_path(path: &str) -> String {
+    unquote_path(path.trim_end_matches(['\t', '\r']))
+}
+
+// gitが引用符で囲み、Cの形式でエスケープしたパスを元に戻す（例: "b/\346\227\245.lock"）
+pub fn unquote_path(path: &str) -> String {
+    let Some(inner) = path
+        .strip_prefix('"')
+        .and_then(|path| path.strip_suffix('"'))
+    else {
+        return path.to_string();
+    };
+
+    let bytes = inner.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] != b'\\' || i + 1 == bytes.len() {
+            out.push(bytes[i]);
+            i += 1;
+            continue;
+        }
+
+        let escaped = bytes[i + 1];
+        i += 2;
+        match escaped {
+            // 8進数で表したバイト（UTF-8のバイト列は1バイトずつエスケープされる）
+            b'0'..=b'7' => {
+                let mut value = u32::from(escaped - b'0');
+                for _ in 0..2 {
+                    match bytes.get(i) {
+                        Some(&digit @ b'0'..=b'7') => {
+                            value = value * 8 + u32::from(digit - b'0');
+                            i += 1;
+                        }
+                        _ => break,
+                    }
+                }
+                out.push(value as u8);
+            }
+            b'a' => out.push(0x07),
+            b'b' => out.push(0x08),
+            b't' => out.push(b'\t'),
+            b'n' => out.push(b'\n'),
+            b'v' => out.push(0x0b),
+            b'f' => out.push(0x0c),
+            b'r' => out.push(b'\r'),
+            other => out.push(other),
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+// 切り詰めた結果
+#[derive(Debug)]
+pub struct Truncation {
+    pub diff: String,
+    // ユーザーに知らせる省略内容（切り詰めていなければ空）
+    pub omitted: Vec<String>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Detail {
+    Full,
+    HeadersOnly,
+    Dropped,
+}
+
+// トークン数の上限に収まるよう差分を切り詰める（max_tokens, max_hunk_linesは0で無制限）
+pub fn fit_to_budget(
+    diff: &str,
+    max_tokens: usize,
+    max_hunk_lines: usize,
+    estimate: impl Fn(&str) -> usize,
+) -> Truncation {
+    if max_tokens == 0 || estimate(diff) <= max_tokens {
+        return Truncation {
+            diff: diff.to_string(),
+            omitted: Vec::new(),
+        };
+    }
+
+    let mut files = parse(diff);
+    // 省略したファイルの変更行数は、ハンクを切り詰める前の行数で数える
+    let summaries: Vec<String> = files.iter().map(FileDiff::summary).collect();
+    let truncated_hunks: Vec<usize> = files
+        .iter_mut()
+        .map(|file| {
+            if max_hunk_lines == 0 {
+                0
+            } else {
+                file.truncate_hunks(max_hunk_lines)
+            }
+        })
+        .collect();
+
+    // 省略したファイルの一覧の分をあらかじめ確保する
+    let mut remaining = max_tokens.saturating_sub(estimate(&summaries.join("\n")));
+
+    // 小さいファイルから順に、差分全体を含められるだけ含める
+    let full_costs: Vec<usize> = files.iter().map(|f| estimate(&f.render())).collect();
+    let mut details = vec![Detail::Dropped; files.len()];
+    let mut order: Vec<usize> = (0..files.len()).collect();
+    order.sort_by_key(|&i| full_costs[i]);
+    for i in order {
+        if full_costs[i] <= remaining {
+            details[i] = Detail::Full;
+            remaining -= full_costs[i];
+        }
+    }
+
+    // 残りはヘッダーだけでも含める
+    for (i, file) in files.iter().enumerate() {
+        if details[i] == Detail::Dropped {
+            let cost = estimate(&file.render_headers());
+            if cost <= remaining {
+                details[i] = Detail::HeadersOnly;
+                remaining -= cost;
+            }
+        }
+    }
+
+    let mut out = String::new();
+    let mut omitted = Vec::new();
+    let mut dropped = Vec::new();
+    for (i, file) in files.iter().enumerate() {
+        match details[i] {
+            Detail::Full => {
+                out.push_str(&file.render());
+                if truncated_hunks[i] > 0 {
+                    omitted.push(format!(
+                        "{}: {} large hunk(s) truncated",
+                        file.path, truncated_hunks[i]
+                    ));
+                }
+            }
+            Detail::HeadersOnly => {
+                out.push_str(&file.render_headers());
+                omitted.push(format!("{}: changed lines omitted", file.path));
+            }
+            Detail::Dropped => {
+                dropped.push(summaries[i].as_str());
+                omitted.push(format!("{}: omitted", summaries[i]));
+            }
+        }
+    }
+
+    if !dropped.is_empty() {
+        out.push_str("\nOther changed files (diff omitted because it is too large):\n");
+        for summary in dropped {
+            push_line(&mut out, summary);
+        }
+    }
+
+    Truncation { diff: out, omitted }
+}
+
+// 要約のために差分を分割する（小さいファイルはまとめ、大きいファイルはハンク単位で分ける）
+pub fn split_chunks(
+    diff: &str,
+    chunk_tokens: usize,
+    estimate: impl Fn(&str) -> usize,
+) -> Vec<String> {
+    let files = parse(diff);
+    if chunk_tokens == 0 {
+        return files.iter().map(FileDiff::render).collect();
+    }
+
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+    let mut cost = 0;
+    for file in &files {
+        for piece in file.split(chunk_tokens, &estimate) {
+            let piece_cost = estimate(&piece);
+            if !current.is_empty() && cost + piece_cost > chunk_tokens {
+                chunks.push(std::mem::take(&mut current));
+                cost = 0;
+            }
+            current.push_str(&piece);
+            cost += piece_cost;
+        }
+    }
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+    chunks
+}
+
+// モデルに送る差分と、内容を省いたファイルの要約
+#[derive(Debug, Default)]
+pub struct StagedDiff {
+    pub diff: String,
+    // "modified Cargo.lock (+120/-80)" の形式
+    pub excluded: Vec<String>,
+}
+
+impl StagedDiff {
+    pub fn is_empty(&self) -> bool {
+        self.diff.is_empty() && self.excluded.is_empty()
+    }
+
+    // 内容を省いたファイルの一覧をプロンプト用に整形する
+    pub fn excluded_note(&self) -> String {
+        if self.excluded.is_empty() {
+            return String::new();
+        }
+
+        let mut note = String::from("\nFiles changed without showing their diff:\n");
+        for summary in &self.excluded {
+            push_line(&mut note, summary);
+        }
+        note
+    }
+}
+
+// 除外対象・バイナリ・自動生成のファイルを差分から取り除き、変更行数の要約だけを残す
+pub fn preprocess(diff: &str, is_excluded: impl Fn(&str) -> bool) -> StagedDiff {
+    let files = parse(diff);
+    let collapse =
+        |file: &FileDiff| is_excluded(&file.path) || file.is_binary() || file.is_generated();
+    if !files.iter().any(collapse) {
+        return StagedDiff {
+            diff: diff.to_string(),
+            excluded: Vec::new(),
+        };
+    }
+
+    let mut staged = StagedDiff::default();
+    for file in &files {
+        if collapse(file) {
+            staged.excluded.push(file.summary());
+        } else {
+            staged.diff.push_str(&file.render());
+        }
+    }
+    staged
+}
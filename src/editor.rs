@@ -4,6 +4,7 @@ use termion::event::Key;
 use termion::input::TermRead;
 use termion::raw::IntoRawMode;
 use termion::{clear, cursor};
+use unicode_width::UnicodeWidthChar;
 
 pub struct Editor {
     content: Vec<String>,
@@ -55,7 +56,8 @@ impl Editor {
         }
     }
 
-    pub fn run(&mut self) -> Result<String> {
+    // 編集した内容を返す（Escで保存せずに閉じた場合はNone）
+    pub fn run(&mut self) -> Result<Option<String>> {
         let stdin = stdin();
         let mut stdout = stdout().into_raw_mode()?;
 
@@ -67,14 +69,8 @@ impl Editor {
         // キー入力処理
         for c in stdin.keys() {
             match c? {
-                // 終了
-                Key::Esc => {
-                    // 画面をクリアして終了メッセージを表示
-                    write!(stdout, "{}{}", clear::All, cursor::Goto(1, 1))?;
-                    write!(stdout, "Editor closed.\n\n")?;
-                    stdout.flush()?;
-                    break;
-                }
+                // 保存せずに終了
+                Key::Esc => break,
                 // 保存して終了
                 Key::Ctrl('s') => {
                     self.message = String::from("Changes saved. Press Esc to exit.");
@@ -93,79 +89,9 @@ impl Editor {
                     write!(stdout, "Changes saved. Editor closed.\n\n")?;
                     stdout.flush()?;
 
-                    return Ok(content);
-                }
-                // カーソル移動
-                Key::Left => {
-                    if self.cursor_x > 0 {
-                        self.cursor_x -= 1;
-                    }
-                }
-                Key::Right => {
-                    if self.cursor_x < self.content[self.cursor_y].len() {
-                        self.cursor_x += 1;
-                    }
-                }
-                Key::Up => {
-                    if self.cursor_y > 0 {
-                        self.cursor_y -= 1;
-                        self.cursor_x =
-                            std::cmp::min(self.cursor_x, self.content[self.cursor_y].len());
-                    }
-                }
-                Key::Down => {
-                    if self.cursor_y < self.content.len() - 1 {
-                        self.cursor_y += 1;
-                        self.cursor_x =
-                            std::cmp::min(self.cursor_x, self.content[self.cursor_y].len());
-                    }
+                    return Ok(Some(content));
                 }
-                // 改行
-                Key::Char('\n') => {
-                    let current_line = &self.content[self.cursor_y];
-                    let new_line = if self.cursor_x < current_line.len() {
-                        current_line[self.cursor_x..].to_string()
-                    } else {
-                        String::new()
-                    };
-
-                    if self.cursor_x < current_line.len() {
-                        self.content[self.cursor_y] = current_line[..self.cursor_x].to_string();
-                    }
-
-                    self.content.insert(self.cursor_y + 1, new_line);
-                    self.cursor_y += 1;
-                    self.cursor_x = 0;
-                }
-                // バックスペース
-                Key::Backspace => {
-                    if self.cursor_x > 0 {
-                        let line = &mut self.content[self.cursor_y];
-                        let mut chars: Vec<char> = line.chars().collect();
-                        chars.remove(self.cursor_x - 1);
-                        *line = chars.into_iter().collect();
-                        self.cursor_x -= 1;
-                    } else if self.cursor_y > 0 {
-                        // 行の先頭で行を削除
-                        let line = self.content.remove(self.cursor_y);
-                        self.cursor_y -= 1;
-                        self.cursor_x = self.content[self.cursor_y].len();
-                        self.content[self.cursor_y].push_str(&line);
-                    }
-                }
-                // 通常文字入力
-                Key::Char(c) => {
-                    if self.cursor_y >= self.content.len() {
-                        self.content.push(String::new());
-                    }
-
-                    let line = &mut self.content[self.cursor_y];
-                    let mut chars: Vec<char> = line.chars().collect();
-                    chars.insert(self.cursor_x, c);
-                    *line = chars.into_iter().collect();
-                    self.cursor_x += 1;
-                }
-                _ => {}
+                key => self.handle_key(key),
             }
 
             // 画面を更新
@@ -174,19 +100,80 @@ impl Editor {
             stdout.flush()?;
         }
 
-        // 編集内容を文字列として返す
-        let mut content = String::new();
-        for line in &self.content {
-            content.push_str(line);
-            content.push('\n');
-        }
-
         // 画面をクリアして終了メッセージを表示
         write!(stdout, "{}{}", clear::All, cursor::Goto(1, 1))?;
         write!(stdout, "Editor closed. No changes saved.\n\n")?;
         stdout.flush()?;
 
-        Ok(content)
+        Ok(None)
+    }
+
+    // 行の文字数（カーソルの位置はバイトではなく文字で数える）
+    fn line_len(&self) -> usize {
+        self.content[self.cursor_y].chars().count()
+    }
+
+    // カーソルの位置に対応する行内のバイト位置
+    fn byte_index(&self) -> usize {
+        let line = &self.content[self.cursor_y];
+        line.char_indices()
+            .nth(self.cursor_x)
+            .map_or(line.len(), |(index, _)| index)
+    }
+
+    // 編集のキー操作
+    pub fn handle_key(&mut self, key: Key) {
+        match key {
+            // カーソル移動
+            Key::Left if self.cursor_x > 0 => {
+                self.cursor_x -= 1;
+            }
+            Key::Right if self.cursor_x < self.line_len() => {
+                self.cursor_x += 1;
+            }
+            Key::Up if self.cursor_y > 0 => {
+                self.cursor_y -= 1;
+                self.cursor_x = std::cmp::min(self.cursor_x, self.line_len());
+            }
+            Key::Down if self.cursor_y < self.content.len() - 1 => {
+                self.cursor_y += 1;
+                self.cursor_x = std::cmp::min(self.cursor_x, self.line_len());
+            }
+            // 改行
+            Key::Char('\n') => {
+                let index = self.byte_index();
+                let new_line = self.content[self.cursor_y].split_off(index);
+
+                self.content.insert(self.cursor_y + 1, new_line);
+                self.cursor_y += 1;
+                self.cursor_x = 0;
+            }
+            // バックスペース
+            Key::Backspace => {
+                if self.cursor_x > 0 {
+                    self.cursor_x -= 1;
+                    let index = self.byte_index();
+                    self.content[self.cursor_y].remove(index);
+                } else if self.cursor_y > 0 {
+                    // 行の先頭で行を削除
+                    let line = self.content.remove(self.cursor_y);
+                    self.cursor_y -= 1;
+                    self.cursor_x = self.line_len();
+                    self.content[self.cursor_y].push_str(&line);
+                }
+            }
+            // 通常文字入力
+            Key::Char(c) => {
+                if self.cursor_y >= self.content.len() {
+                    self.content.push(String::new());
+                }
+
+                let index = self.byte_index();
+                self.content[self.cursor_y].insert(index, c);
+                self.cursor_x += 1;
+            }
+            _ => {}
+        }
     }
 
     fn draw<W: Write>(&self, stdout: &mut W) -> Result<()> {
@@ -203,11 +190,16 @@ impl Editor {
             self.message
         )?;
 
-        // カーソル位置を設定
+        // カーソル位置を設定（全角文字は2桁分の幅で数える）
+        let column: usize = self.content[self.cursor_y]
+            .chars()
+            .take(self.cursor_x)
+            .filter_map(UnicodeWidthChar::width)
+            .sum();
         write!(
             stdout,
             "{}",
-            cursor::Goto((self.cursor_x + 1) as u16, (self.cursor_y + 1) as u16)
+            cursor::Goto((column + 1) as u16, (self.cursor_y + 1) as u16)
         )?;
 
         Ok(())
@@ -222,17 +214,17 @@ impl Editor {
             lines.push(line.to_string());
         }
         self.content = lines;
-        
+
         // テキストに行を追加
         self.content.push("Test added line".to_string());
-        
+
         // 内容を文字列として返す
         let mut result = String::new();
         for line in &self.content {
             result.push_str(line);
             result.push('\n');
         }
-        
+
         result
     }
 }
@@ -0,0 +1,89 @@
+use anyhow::{Context, Result};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use std::fs;
+use std::path::Path;
+
+// リポジトリ直下に置く除外パターンのファイル
+pub const IGNORE_FILE: &str = ".aicignore";
+
+// 差分の内容をモデルに送らないファイルのパターン
+//   .gitignoreと同様に、"/"を含まないパターンはどの階層のファイルにも一致し、
+//   "/"で終わるパターンはディレクトリ以下に、"!"で始まるパターンは除外の取り消しになる
+pub struct ExcludeList {
+    set: GlobSet,
+    // パターンごとの取り消しかどうか
+    negated: Vec<bool>,
+}
+
+impl ExcludeList {
+    pub fn new<S: AsRef<str>>(patterns: &[S]) -> Result<Self> {
+        let mut builder = GlobSetBuilder::new();
+        let mut negated = Vec::new();
+
+        for pattern in patterns {
+            let pattern = pattern.as_ref().trim();
+            if pattern.is_empty() || pattern.starts_with('#') {
+                continue;
+            }
+
+            let (negate, pattern) = match pattern.strip_prefix('!') {
+                Some(rest) => (true, rest),
+                None => (false, pattern),
+            };
+
+            let glob = Glob::new(&normalize(pattern))
+                .with_context(|| format!("Invalid exclude pattern: {}", pattern))?;
+            builder.add(glob);
+            negated.push(negate);
+        }
+
+        Ok(Self {
+            set: builder.build()?,
+            negated,
+        })
+    }
+
+    // 設定ファイルのパターンと、リポジトリの .aicignore を合わせて読み込む
+    pub fn load(patterns: &[String], repo_root: Option<&Path>) -> Result<Self> {
+        let mut all: Vec<String> = patterns.to_vec();
+
+        if let Some(root) = repo_root {
+            let path = root.join(IGNORE_FILE);
+            if path.exists() {
+                let content = fs::read_to_string(&path)
+                    .with_context(|| format!("Failed to read {}", path.display()))?;
+                all.extend(content.lines().map(str::to_string));
+            }
+        }
+
+        Self::new(&all)
+    }
+
+    // 最後に一致したパターンで判定する
+    pub fn is_excluded(&self, path: &str) -> bool {
+        self.set
+            .matches(path)
+            .into_iter()
+            .max()
+            .is_some_and(|index| !self.negated[index])
+    }
+}
+
+// .gitignore風のパターンをリポジトリ直下からのglobに変換する
+pub fn normalize(pattern: &str) -> String {
+    let (pattern, dir_only) = match pattern.strip_suffix('/') {
+        Some(rest) => (rest, true),
+        None => (pattern, false),
+    };
+
+    let mut glob = match pattern.strip_prefix('/') {
+        Some(anchored) => anchored.to_string(),
+        None if pattern.contains('/') => pattern.to_string(),
+        None => format!("**/{}", pattern),
+    };
+
+    if dir_only {
+        glob.push_str("/**");
+    }
+    glob
+}
@@ -0,0 +1,216 @@
+use anyhow::{Context, Result, anyhow};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+pub const HOOK_NAME: &str = "prepare-commit-msg";
+
+// aicをインストールする前からあったフックの退避先（aicのフックから先に呼び出す）
+pub const CHAINED_HOOK_NAME: &str = "prepare-commit-msg.pre-aic";
+
+// aicがインストールしたフックであることを示す行
+const MARKER: &str = "# Installed by `aic hook install`";
+
+// フックのスクリプト（インストール時の実行ファイルが見つからなければPATHのaicを使う）
+pub fn hook_script(exe: &Path) -> String {
+    format!(
+        r#"#!/bin/sh
+{marker}. Remove it with `aic hook uninstall`.
+hook_dir=$(dirname "$0")
+if [ -x "$hook_dir/{chained}" ]; then
+    "$hook_dir/{chained}" "$@" || exit $?
+fi
+aic={exe}
+if [ ! -x "$aic" ]; then
+    aic=aic
+fi
+"$aic" hook run "$@" || true
+"#,
+        marker = MARKER,
+        chained = CHAINED_HOOK_NAME,
+        exe = shell_quote(&exe.display().to_string())
+    )
+}
+
+// shの単一引用符で囲む（$や`を展開させない）
+fn shell_quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', r"'\''"))
+}
+
+pub fn is_aic_hook(content: &str) -> bool {
+    content.contains(MARKER)
+}
+
+// 生成するかどうかをgitが渡すメッセージの由来から判定する
+//   message: -m/-F、merge: マージ、squash: スカッシュ、commit: --amend/-c/-C
+pub fn should_generate(source: Option<&str>) -> bool {
+    matches!(source, None | Some("") | Some("template"))
+}
+
+// 生成したメッセージをコミットメッセージのファイルの先頭に書き込む
+//   gitが用意したコメントやテンプレートはその後ろに残す
+pub fn insert_message(existing: &str, message: &str) -> String {
+    let message = message.trim();
+    if existing.trim().is_empty() {
+        return format!("{}\n", message);
+    }
+    if existing.starts_with('\n') {
+        format!("{}\n{}", message, existing)
+    } else {
+        format!("{}\n\n{}", message, existing)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum InstallOutcome {
+    Installed,
+    // 既存のフックを退避して、aicのフックから呼び出すようにした
+    Chained,
+    AlreadyInstalled,
+}
+
+pub fn install(hooks_dir: &Path, exe: &Path) -> Result<InstallOutcome> {
+    fs::create_dir_all(hooks_dir)
+        .with_context(|| format!("Failed to create {}", hooks_dir.display()))?;
+    let hook_path = hooks_dir.join(HOOK_NAME);
+    let chained_path = hooks_dir.join(CHAINED_HOOK_NAME);
+
+    let mut outcome = InstallOutcome::Installed;
+    if hook_path.exists() {
+        let existing = fs::read_to_string(&hook_path).unwrap_or_default();
+        if is_aic_hook(&existing) {
+            outcome = InstallOutcome::AlreadyInstalled;
+        } else {
+            if chained_path.exists() {
+                return Err(anyhow!(
+                    "Both {} and {} already exist. Remove one of them and try again.",
+                    hook_path.display(),
+                    chained_path.display()
+                ));
+            }
+            fs::rename(&hook_path, &chained_path)
+                .with_context(|| format!("Failed to move {}", hook_path.display()))?;
+            outcome = InstallOutcome::Chained;
+        }
+    }
+
+    // 実行ファイルのパスが変わった場合に備えて、インストール済みでも書き直す
+    fs::write(&hook_path, hook_script(exe))
+        .with_context(|| format!("Failed to write {}", hook_path.display()))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&hook_path, fs::Permissions::from_mode(0o755))?;
+    }
+    Ok(outcome)
+}
+
+// aicのフックを削除し、退避していたフックを元に戻す（戻した場合はtrue）
+pub fn uninstall(hooks_dir: &Path) -> Result<bool> {
+    let hook_path = hooks_dir.join(HOOK_NAME);
+    let chained_path = hooks_dir.join(CHAINED_HOOK_NAME);
+
+    let existing = fs::read_to_string(&hook_path).map_err(|_| {
+        anyhow!(
+            "No {} hook is installed in {}",
+            HOOK_NAME,
+            hooks_dir.display()
+        )
+    })?;
+    if !is_aic_hook(&existing) {
+        return Err(anyhow!(
+            "{} was not installed by aic. Leaving it unchanged.",
+            hook_path.display()
+        ));
+    }
+
+    fs::remove_file(&hook_path)?;
+    if chained_path.exists() {
+        fs::rename(&chained_path, &hook_path)
+            .with_context(|| format!("Failed to restore {}", chained_path.display()))?;
+        return Ok(true);
+    }
+    Ok(false)
+}
+
+// フックを置くディレクトリ（core.hooksPathやworktreeも考慮する）
+pub fn hooks_dir() -> Result<PathBuf> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--git-path", "hooks"])
+        .output()?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "Not a git repository: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    let dir = PathBuf::from(String::from_utf8_lossy(&output.stdout).trim_end());
+    Ok(std::env::current_dir()?.join(dir))
+}
+
+// core.hooksPathが設定されていればその値
+pub fn hooks_path_setting() -> Option<String> {
+    let output = Command::new("git")
+        .args(["config", "core.hooksPath"])
+        .output()
+        .ok()?;
+    let value = String::from_utf8(output.stdout).ok()?;
+    Some(value.trim().to_string()).filter(|value| !value.is_empty())
+}
+
+pub fn install_hook() -> Result<()> {
+    let dir = hooks_dir()?;
+    let exe = std::env::current_exe()?;
+    let hook_path = dir.join(HOOK_NAME);
+
+    match install(&dir, &exe)? {
+        InstallOutcome::Installed => println!("Installed {}.", hook_path.display()),
+        InstallOutcome::Chained => println!(
+            "Installed {}. The existing hook was moved to {} and still runs first.",
+            hook_path.display(),
+            CHAINED_HOOK_NAME
+        ),
+        InstallOutcome::AlreadyInstalled => {
+            println!("{} is already installed. Updated it.", hook_path.display())
+        }
+    }
+    if let Some(setting) = hooks_path_setting() {
+        println!("Note: core.hooksPath is set to {}.", setting);
+    }
+    println!("`git commit` will now start with a generated message.");
+    Ok(())
+}
+
+pub fn uninstall_hook() -> Result<()> {
+    let dir = hooks_dir()?;
+    if uninstall(&dir)? {
+        println!(
+            "Removed the aic hook and restored the previous {} hook.",
+            HOOK_NAME
+        );
+    } else {
+        println!("Removed {}.", dir.join(HOOK_NAME).display());
+    }
+    Ok(())
+}
+
+pub fn hook_status() -> Result<()> {
+    let dir = hooks_dir()?;
+    let hook_path = dir.join(HOOK_NAME);
+
+    println!("Hooks directory: {}", dir.display());
+    if let Some(setting) = hooks_path_setting() {
+        println!("core.hooksPath: {}", setting);
+    }
+    let status = match fs::read_to_string(&hook_path) {
+        Ok(content) if is_aic_hook(&content) => "Installed",
+        Ok(_) => "Not installed (another prepare-commit-msg hook exists)",
+        Err(_) => "Not installed",
+    };
+    println!("aic hook: {}", status);
+    if dir.join(CHAINED_HOOK_NAME).exists() {
+        println!("Previous hook: {} (runs first)", CHAINED_HOOK_NAME);
+    }
+    Ok(())
+}
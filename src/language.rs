@@ -1,14 +1,51 @@
+use promptuity::{
+    Promptuity, Term,
+    prompts::{Select, SelectOption},
+    themes::FancyTheme,
+};
 use serde::{Deserialize, Serialize};
-use promptuity::{prompts::{Select, SelectOption}, themes::FancyTheme, Promptuity, Term};
 
-#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
 pub enum Language {
+    #[default]
     Japanese,
     English,
     Chinese,
 }
 
 impl Language {
+    pub const ALL: [Language; 3] = [Language::Japanese, Language::English, Language::Chinese];
+
+    // 言語名または言語コード（ja, en, zh）から取得する
+    pub fn from_name(name: &str) -> anyhow::Result<Language> {
+        let wanted = crate::config::normalize_name(name);
+        Language::ALL
+            .into_iter()
+            .find(|language| {
+                crate::config::normalize_name(language.as_str()) == wanted
+                    || language.code() == wanted
+            })
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Unknown language: {}. Available languages: {}",
+                    name,
+                    Language::ALL
+                        .iter()
+                        .map(|language| language.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            })
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            Language::Japanese => "ja",
+            Language::English => "en",
+            Language::Chinese => "zh",
+        }
+    }
+
     pub fn as_str(&self) -> &'static str {
         match self {
             Language::Japanese => "Japanese",
@@ -56,18 +93,12 @@ Please generate the commit message in Chinese.",
     }
 }
 
-impl Default for Language {
-    fn default() -> Self {
-        Language::Japanese
-    }
-}
-
 pub fn select_language() -> anyhow::Result<Language> {
     let mut term = Term::default();
     let mut theme = FancyTheme::default();
     let mut p = Promptuity::new(&mut term, &mut theme);
 
-    let options = vec![
+    let options = [
         ("Japanese (日本語)", Language::Japanese),
         ("English", Language::English),
         ("Chinese (中文)", Language::Chinese),
@@ -89,7 +120,7 @@ pub fn select_language() -> anyhow::Result<Language> {
         .iter()
         .find(|(label, _)| label == &selected)
         .map(|(_, lang)| *lang)
-        .unwrap_or(Language::default());
+        .unwrap_or_default();
 
     Ok(selected_language)
 }
@@ -1,14 +1,27 @@
 use anyhow::{Result, anyhow};
 use clap::{Parser, Subcommand};
 use dotenv::dotenv;
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
 use std::process::Command;
-use tokio;
 
 mod api;
 mod config;
+mod conventional;
+mod credentials;
+mod diff;
 mod editor;
+mod exclude;
+mod hook;
 mod language;
-use config::Config;
+mod review;
+mod secrets;
+mod summarize;
+use api::{Generation, TokenCallback};
+use config::{Config, SecretAction};
+use diff::StagedDiff;
+use exclude::ExcludeList;
+use review::ReviewAction;
 
 #[cfg(test)]
 mod tests;
@@ -30,12 +43,63 @@ struct Args {
         help = "Use the generated message to commit automatically"
     )]
     commit: bool,
+
+    #[arg(long, help = "Wait for the whole message instead of streaming it")]
+    no_stream: bool,
+
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        help = "Total timeout for each API request in seconds"
+    )]
+    timeout: Option<u64>,
+
+    #[arg(
+        long,
+        help = "Summarize a diff that exceeds the token budget instead of truncating it"
+    )]
+    summarize: bool,
+
+    #[arg(long, help = "Send the diff without detecting and redacting secrets")]
+    no_redact: bool,
+
+    #[arg(
+        long,
+        help = "Print the message and the git command instead of asking what to do with it"
+    )]
+    no_review: bool,
+
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = 1,
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..=10),
+        help = "Generate N alternative messages and choose one"
+    )]
+    candidates: usize,
+
+    #[arg(
+        long,
+        help = "Write the message in the Conventional Commits format and validate it"
+    )]
+    conventional: bool,
+
+    #[arg(
+        long,
+        global = true,
+        value_name = "NAME",
+        help = "Use a named configuration profile (defaults to $AIC_PROFILE)"
+    )]
+    profile: Option<String>,
 }
 
 #[derive(Subcommand, Debug)]
 enum Commands {
     /// Set or get API configuration
     Config {
+        #[command(subcommand)]
+        command: Option<ConfigCommand>,
+
         /// Set API Key interactively
         #[arg(long, help = "Set API key interactively")]
         api: bool,
@@ -52,10 +116,70 @@ enum Commands {
         #[arg(long, help = "Set custom prompt for commit messages (interactive)")]
         prompt: bool,
     },
+    /// Manage the Git prepare-commit-msg hook
+    Hook {
+        #[command(subcommand)]
+        command: HookCommand,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum HookCommand {
+    /// Install a prepare-commit-msg hook that fills in the generated message
+    Install,
+    /// Remove the hook and restore the previous one
+    Uninstall,
+    /// Show whether the hook is installed
+    Status,
+    /// Run as the prepare-commit-msg hook (called by Git)
+    Run {
+        msg_file: PathBuf,
+        source: Option<String>,
+        sha: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum ConfigCommand {
+    /// Manage named configuration profiles
+    Profile {
+        #[command(subcommand)]
+        command: ProfileCommand,
+    },
+    /// Set a configuration value (platform, model, language or prompt)
+    Set {
+        key: String,
+        #[arg(required_unless_present = "file")]
+        value: Option<String>,
+        /// Read the custom prompt from a file ("-" for standard input)
+        #[arg(long, value_name = "PATH", conflicts_with = "value")]
+        file: Option<PathBuf>,
+    },
+    /// Print the effective value of a configuration key
+    Get { key: String },
+    /// Reset a configuration value to its default
+    Unset { key: String },
+    /// Move API keys stored in plain text in the configuration file to the OS keyring
+    MigrateKeys,
 }
 
-async fn get_staged_diff() -> Result<String> {
-    let output = Command::new("git").args(["diff", "--staged"]).output()?;
+#[derive(Subcommand, Debug)]
+enum ProfileCommand {
+    /// Create or update a profile interactively
+    Add { name: String },
+    /// List profiles (the active one is marked with *)
+    List,
+    /// Use a profile by default (select interactively if NAME is omitted)
+    Use { name: Option<String> },
+    /// Remove a profile (select interactively if NAME is omitted)
+    Remove { name: Option<String> },
+}
+
+async fn get_staged_diff(config: &Config) -> Result<StagedDiff> {
+    // 日本語などのパスを引用符やエスケープなしで受け取る
+    let output = Command::new("git")
+        .args(["-c", "core.quotePath=false", "diff", "--staged"])
+        .output()?;
 
     if !output.status.success() {
         return Err(anyhow!(
@@ -64,23 +188,152 @@ async fn get_staged_diff() -> Result<String> {
         ));
     }
 
-    Ok(String::from_utf8(output.stdout)?)
+    let diff = diff::decode(&output.stdout);
+
+    // 除外パターンに一致するファイルやバイナリ、自動生成のファイルは変更行数だけを伝える
+    let excludes = ExcludeList::load(&config.diff.exclude, config::get_repo_root().as_deref())?;
+    Ok(diff::preprocess(&diff, |path| excludes.is_excluded(path)))
 }
 
-async fn generate_commit_message(diff: &str) -> Result<String> {
-    // 設定を読み込み
-    let config = Config::load()?;
-    let api_key = config.get_api_key()?;
-    let language = config.language;
-    let platform = config.platform;
-    let model = config.get_model_name();
+// プロンプトに含める変更内容
+enum Changes {
+    Diff {
+        // メインのプラットフォームのトークン数の上限に合わせて切り詰めた差分
+        diff: String,
+        // 切り詰める前の差分（フォールバック先の上限に合わせて切り詰め直すため）
+        full: String,
+        // 除外したファイルの要約
+        note: String,
+    },
+    // 大きな差分を分割して要約したもの
+    Summaries(String),
+}
 
-    // システムプロンプトと言語に応じたユーザープロンプトを取得
-    let system_prompt = match &config.custom_prompt {
-        Some(custom_prompt) => custom_prompt.as_str(),
-        None => language.system_prompt(),
+// 設定を読み込み、コマンドライン引数での指定を反映する
+fn load_config(args: &Args) -> Result<Config> {
+    let mut config = Config::load_profile(args.profile.as_deref())?;
+    if let Some(timeout) = args.timeout {
+        config.timeout.total_secs = timeout;
+    }
+
+    if args.summarize {
+        config.diff.summarize = true;
+    }
+    if args.conventional {
+        config.conventional.enabled = true;
+    }
+    Ok(config)
+}
+
+// ステージしたファイルのパスから、Conventional Commits形式のscopeを決める
+fn detect_scopes(config: &Config) -> Result<Vec<String>> {
+    let conventional = &config.conventional;
+    if !conventional.enabled || conventional.path_scopes.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let output = Command::new("git")
+        .args(["diff", "--staged", "--name-only", "-z"])
+        .output()?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "Failed to list staged files: {}",
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let paths: Vec<&str> = stdout.split('\0').filter(|path| !path.is_empty()).collect();
+
+    let scopes = conventional::ScopeMap::new(&conventional.path_scopes)?.scopes_for(&paths);
+    if scopes.len() > 1 {
+        eprintln!(
+            "Warning: The staged changes span multiple scopes ({}). Consider committing them separately.",
+            scopes.join(", ")
+        );
+    }
+    Ok(scopes)
+}
+
+// ステージされた差分から、プロンプトに含める変更内容を用意する（変更がなければNone）
+async fn prepare_changes(config: &Config, no_redact: bool) -> Result<Option<Changes>> {
+    // ステージされた差分を取得
+    let mut staged = get_staged_diff(config).await?;
+
+    if staged.is_empty() {
+        return Ok(None);
+    }
+
+    // 差分を送信する前に秘密情報を検出し、伏せ字にするか中止する
+    if !no_redact {
+        let scanner = secrets::Scanner::new(&config.secrets)?;
+        let (redacted, findings) = scanner.redact(&staged.diff);
+        if !findings.is_empty() {
+            let report = findings
+                .iter()
+                .map(|finding| format!("  - {}", finding))
+                .collect::<Vec<_>>()
+                .join("\n");
+            if config.secrets.action == SecretAction::Abort {
+                return Err(anyhow!(
+                    "Possible secrets found in the staged diff:\n{}\nRemove them, add them to the secrets allowlist, or run with --no-redact.",
+                    report
+                ));
+            }
+            eprintln!("Redacted possible secrets before sending:\n{}", report);
+            staged.diff = redacted;
+        }
+    }
+
+    if !staged.excluded.is_empty() {
+        eprintln!("Diff omitted from the prompt (summarized in one line):");
+        for summary in &staged.excluded {
+            eprintln!("  - {}", summary);
+        }
+    }
+    let diff = &staged.diff;
+    let note = staged.excluded_note();
+
+    // トークン数の上限を超える差分は要約するか切り詰め、省略した内容を知らせる
+    // （除外したファイルの要約はそのまま後ろに付ける）
+    let provider = api::provider_for(config.platform, config);
+    let estimate = |text: &str| provider.estimate_tokens(text);
+    let budget = config.diff_token_budget();
+    let changes = if config.diff.summarize && budget > 0 && estimate(diff) > budget {
+        Changes::Summaries(summarize::summarize_diff(diff, config, estimate).await? + &note)
+    } else {
+        Changes::Diff {
+            diff: fit_diff(diff, budget, config, estimate),
+            full: staged.diff,
+            note,
+        }
     };
-    let user_prompt = match language {
+
+    Ok(Some(changes))
+}
+
+// 差分をトークン数の上限に収め、省略した内容を知らせる
+fn fit_diff(
+    diff: &str,
+    budget: usize,
+    config: &Config,
+    estimate: impl Fn(&str) -> usize,
+) -> String {
+    let truncation = diff::fit_to_budget(diff, budget, config.diff.max_hunk_lines, estimate);
+    if !truncation.omitted.is_empty() {
+        eprintln!(
+            "The staged diff exceeds the token budget (~{} tokens). Omitted from the prompt:",
+            budget
+        );
+        for note in &truncation.omitted {
+            eprintln!("  - {}", note);
+        }
+    }
+    truncation.diff
+}
+
+// 差分から言語に応じたユーザープロンプトを作る
+fn diff_prompt(diff: &str, language: language::Language) -> String {
+    match language {
         language::Language::Japanese => format!(
             "以下のGit差分に基づいてコミットメッセージを生成してください：\n\n```\n{}\n```",
             diff
@@ -92,10 +345,240 @@ async fn generate_commit_message(diff: &str) -> Result<String> {
         language::Language::Chinese => {
             format!("根据以下Git差异生成提交消息：\n\n```\n{}\n```", diff)
         }
+    }
+}
+
+// scopesはステージしたファイルのパスから決まったConventional Commitsのscope
+// extraは再生成時の指示など、プロンプトの最後に付け加える文
+// candidatesが2以上の場合は複数の候補を生成する
+async fn generate_commit_message(
+    changes: &Changes,
+    config: &Config,
+    scopes: &[String],
+    extra: Option<&str>,
+    candidates: usize,
+    on_token: Option<TokenCallback<'_>>,
+) -> Result<Generation> {
+    let language = config.language;
+
+    // システムプロンプトと言語に応じたユーザープロンプトを取得
+    let system_prompt = match &config.custom_prompt {
+        Some(custom_prompt) => custom_prompt.as_str(),
+        None => language.system_prompt(),
+    };
+    let system_prompt = if config.conventional.enabled {
+        format!(
+            "{}\n\n{}",
+            system_prompt,
+            conventional::instructions(&config.conventional, scopes)
+        )
+    } else {
+        system_prompt.to_string()
+    };
+    let user_prompt = match (changes, language) {
+        (Changes::Diff { diff, note, .. }, language) => {
+            diff_prompt(&format!("{}{}", diff, note), language)
+        }
+        (Changes::Summaries(summaries), language::Language::Japanese) => format!(
+            "差分が大きいため、部分ごとの要約を示します。これらの要約に基づいてコミットメッセージを生成してください：\n\n{}",
+            summaries
+        ),
+        (Changes::Summaries(summaries), language::Language::English) => format!(
+            "The Git diff is too large, so here are summaries of its parts. Generate a commit message based on these summaries:\n\n{}",
+            summaries
+        ),
+        (Changes::Summaries(summaries), language::Language::Chinese) => format!(
+            "Git差异过大，以下是各部分的摘要。请根据这些摘要生成提交消息：\n\n{}",
+            summaries
+        ),
+    };
+    let user_prompt = user_prompt + extra.unwrap_or_default();
+
+    // フォールバック先のトークン数の上限がメインのプラットフォームより小さい場合は、差分を切り詰め直す
+    let primary_budget = config.diff_token_budget();
+    let user_prompt_for = |provider: &dyn api::Provider| {
+        let Changes::Diff { diff, full, note } = changes else {
+            return None;
+        };
+        let budget = config.diff_token_budget_for(provider);
+        let estimate = |text: &str| provider.estimate_tokens(text);
+        // 0は無制限
+        let smaller = budget != 0 && (primary_budget == 0 || budget < primary_budget);
+        if !smaller || estimate(diff) <= budget {
+            return None;
+        }
+        let diff = fit_diff(full, budget, config, estimate);
+        Some(diff_prompt(&format!("{}{}", diff, note), language) + extra.unwrap_or_default())
     };
 
     // APIモジュールを使用してコミットメッセージを生成
-    api::generate_commit_message(platform, &api_key, &model, system_prompt, &user_prompt).await
+    if candidates > 1 {
+        return api::generate_candidates(
+            config,
+            &system_prompt,
+            &user_prompt,
+            Some(&user_prompt_for),
+            candidates,
+        )
+        .await;
+    }
+    api::generate_commit_message(
+        config,
+        &system_prompt,
+        &user_prompt,
+        Some(&user_prompt_for),
+        on_token,
+    )
+    .await
+}
+
+// コミットメッセージを生成して表示する（ストリーミング時は受信したトークンを逐次表示）
+async fn generate_and_print(
+    changes: &Changes,
+    config: &Config,
+    scopes: &[String],
+    extra: Option<&str>,
+    stream: bool,
+) -> Result<String> {
+    let generation = if stream {
+        println!("\nGenerated commit message:");
+        let print_token = |token: &str| {
+            print!("{}", token);
+            io::stdout().flush().ok();
+        };
+        let generation =
+            generate_commit_message(changes, config, scopes, extra, 1, Some(&print_token)).await?;
+
+        // ストリーミング非対応のプラットフォーム（途中で失敗した後のフォールバック先を含む）ではまとめて表示する
+        if generation.streamed {
+            println!();
+        } else {
+            println!("{}", generation.message);
+        }
+        generation
+    } else {
+        let generation = generate_commit_message(changes, config, scopes, extra, 1, None).await?;
+        println!("\nGenerated commit message:\n{}", generation.message);
+        generation
+    };
+
+    print_fallback_notice(&generation, config);
+    Ok(generation.message)
+}
+
+// フォールバック先で生成された場合は実際のプロバイダーを表示
+fn print_fallback_notice(generation: &Generation, config: &Config) {
+    if generation.platform != config.platform || generation.model != config.get_model_name() {
+        println!(
+            "\n(Generated with {} ({}) after {} failed)",
+            generation.platform.as_str(),
+            generation.model,
+            config.platform.as_str()
+        );
+    }
+}
+
+// 複数の候補を生成し、端末から実行された場合は選ばせる
+async fn generate_and_choose(
+    changes: &Changes,
+    config: &Config,
+    scopes: &[String],
+    extra: Option<&str>,
+    count: usize,
+) -> Result<String> {
+    let generation = generate_commit_message(changes, config, scopes, extra, count, None).await?;
+    print_fallback_notice(&generation, config);
+
+    let candidates = &generation.candidates;
+    if candidates.len() < count {
+        println!(
+            "\n{} distinct message(s) generated out of {} requested.",
+            candidates.len(),
+            count
+        );
+    }
+    for (index, candidate) in candidates.iter().enumerate() {
+        println!("\n[{}]\n{}", index + 1, candidate);
+    }
+    println!();
+
+    let message = if candidates.len() > 1 && io::stdin().is_terminal() {
+        review::select_candidate(candidates)?
+    } else {
+        generation.message.clone()
+    };
+    println!("\nGenerated commit message:\n{}", message);
+    Ok(message)
+}
+
+// 候補の数に応じて、1つを生成して表示するか、複数から選ぶ
+async fn generate_message(
+    changes: &Changes,
+    config: &Config,
+    scopes: &[String],
+    extra: Option<&str>,
+    stream: bool,
+    candidates: usize,
+) -> Result<String> {
+    let message = if candidates > 1 {
+        generate_and_choose(changes, config, scopes, extra, candidates).await?
+    } else {
+        generate_and_print(changes, config, scopes, extra, stream).await?
+    };
+
+    // 直したり生成し直したりした場合は、実際に使うメッセージを表示する
+    let conformed = conform(changes, config, scopes, &message, extra).await?;
+    if conformed.trim() != message.trim() {
+        println!("\nCommit message:\n{}", conformed);
+    }
+    Ok(conformed)
+}
+
+// Conventional Commits形式が有効な場合、メッセージを検証する
+//   機械的に直せるものは直し、直せなければ問題点を伝えて生成し直す
+//   extraは最初の生成に使った指示で、生成し直すときも引き継ぐ
+async fn conform(
+    changes: &Changes,
+    config: &Config,
+    scopes: &[String],
+    message: &str,
+    extra: Option<&str>,
+) -> Result<String> {
+    if !config.conventional.enabled {
+        return Ok(message.to_string());
+    }
+
+    let retries = config.conventional.retries;
+    let mut message = message.to_string();
+    for attempt in 0..=retries {
+        let repaired = conventional::repair(&message, &config.conventional, scopes);
+        let problems = conventional::validate(&repaired, &config.conventional, scopes);
+        if problems.is_empty() {
+            if repaired != message.trim() {
+                eprintln!("\nAdjusted the message to the Conventional Commits format.");
+            }
+            return Ok(repaired);
+        }
+        if attempt == retries {
+            eprintln!(
+                "\nWarning: The message does not follow the Conventional Commits format: {}",
+                problems.join("; ")
+            );
+            return Ok(repaired);
+        }
+
+        eprintln!(
+            "\nThe message does not follow the Conventional Commits format ({}). Regenerating...",
+            problems.join("; ")
+        );
+        let revision =
+            review::revision_request(&repaired, &conventional::fix_instructions(&problems));
+        let revision = format!("{}{}", extra.unwrap_or_default(), revision);
+        let generation =
+            generate_commit_message(changes, config, scopes, Some(&revision), 1, None).await?;
+        message = generation.message;
+    }
+    Ok(message)
 }
 
 async fn commit_with_message(message: &str) -> Result<()> {
@@ -114,58 +597,211 @@ async fn commit_with_message(message: &str) -> Result<()> {
     Ok(())
 }
 
+// prepare-commit-msg フックとして実行し、生成したメッセージをファイルに書き込む
+async fn run_hook(args: &Args, msg_file: &Path, source: Option<&str>) -> Result<()> {
+    // -m、マージ、スカッシュ、--amend などメッセージが決まっている場合は何もしない
+    if !hook::should_generate(source) {
+        return Ok(());
+    }
+
+    // 生成に失敗してもコミットは妨げない
+    if let Err(e) = write_hook_message(args, msg_file).await {
+        eprintln!("aic: could not generate a commit message: {:#}", e);
+        if let Some(hint) = api::error_kind(&e).and_then(|k| k.hint()) {
+            eprintln!("Hint: {}", hint);
+        }
+    }
+    Ok(())
+}
+
+async fn write_hook_message(args: &Args, msg_file: &Path) -> Result<()> {
+    let config = load_config(args)?;
+    let Some(changes) = prepare_changes(&config, args.no_redact).await? else {
+        return Ok(());
+    };
+    let scopes = detect_scopes(&config)?;
+
+    eprintln!(
+        "Generating commit message using {} ({})...",
+        config.platform.as_str(),
+        config.get_model_name()
+    );
+    let generation = generate_commit_message(&changes, &config, &scopes, None, 1, None).await?;
+    let message = conform(&changes, &config, &scopes, &generation.message, None).await?;
+
+    let existing = std::fs::read_to_string(msg_file).unwrap_or_default();
+    std::fs::write(msg_file, hook::insert_message(&existing, &message))
+        .map_err(|e| anyhow!("Failed to write {}: {}", msg_file.display(), e))?;
+    Ok(())
+}
+
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() {
     // .envファイルから環境変数を読み込む
     dotenv().ok();
 
     // コマンドライン引数の解析
     let args = Args::parse();
 
+    // APIエラーの場合は対処方法を表示し、種類ごとの終了コードで終了する
+    if let Err(e) = run(args).await {
+        eprintln!("Error: {:?}", e);
+        let kind = api::error_kind(&e);
+        if let Some(hint) = kind.and_then(|k| k.hint()) {
+            eprintln!("\nHint: {}", hint);
+        }
+        std::process::exit(kind.map_or(1, |k| k.exit_code()));
+    }
+}
+
+async fn run(args: Args) -> Result<()> {
     // サブコマンドの処理
     if let Some(command) = &args.command {
         match command {
             Commands::Config {
+                command: Some(ConfigCommand::Profile { command }),
+                ..
+            } => {
+                return match command {
+                    ProfileCommand::Add { name } => config::add_profile(name).await,
+                    ProfileCommand::List => config::list_profiles(),
+                    ProfileCommand::Use { name } => config::use_profile(name.as_deref()),
+                    ProfileCommand::Remove { name } => config::remove_profile(name.as_deref()),
+                };
+            }
+            Commands::Config {
+                command: Some(ConfigCommand::Set { key, value, file }),
+                ..
+            } => {
+                return config::set_value(
+                    key,
+                    value.as_deref(),
+                    file.as_deref(),
+                    args.profile.as_deref(),
+                )
+                .await;
+            }
+            Commands::Config {
+                command: Some(ConfigCommand::Get { key }),
+                ..
+            } => return config::get_value(key, args.profile.as_deref()),
+            Commands::Config {
+                command: Some(ConfigCommand::Unset { key }),
+                ..
+            } => return config::unset_value(key, args.profile.as_deref()),
+            Commands::Config {
+                command: Some(ConfigCommand::MigrateKeys),
+                ..
+            } => return config::migrate_keys(),
+            Commands::Config {
+                command: None,
                 api,
                 show,
                 language,
                 prompt,
             } => {
-                return config::handle_config_command(api, show, language, prompt).await;
+                return config::handle_config_command(
+                    api,
+                    show,
+                    language,
+                    prompt,
+                    args.profile.as_deref(),
+                )
+                .await;
+            }
+            Commands::Hook { command } => {
+                return match command {
+                    HookCommand::Install => hook::install_hook(),
+                    HookCommand::Uninstall => hook::uninstall_hook(),
+                    HookCommand::Status => hook::hook_status(),
+                    HookCommand::Run {
+                        msg_file, source, ..
+                    } => run_hook(&args, msg_file, source.as_deref()).await,
+                };
             }
         }
     }
 
-    // ステージされた差分を取得
-    let diff = get_staged_diff().await?;
+    let config = load_config(&args)?;
 
-    if diff.is_empty() {
+    let Some(changes) = prepare_changes(&config, args.no_redact).await? else {
         println!("No staged changes found.");
         return Ok(());
-    }
+    };
+    let scopes = detect_scopes(&config)?;
 
-    // 設定を読み込み、使用するAIプラットフォームとモデルを表示
-    let config = Config::load()?;
     println!(
         "Generating commit message using {} ({})",
         config.platform.as_str(),
         config.get_model_name()
     );
 
-    // コミットメッセージの生成
-    let commit_message = generate_commit_message(&diff).await?;
-
-    println!("\nGenerated commit message:\n{}", commit_message);
+    let stream = config.stream && !args.no_stream;
+    let mut commit_message =
+        generate_message(&changes, &config, &scopes, None, stream, args.candidates).await?;
 
     // 自動コミットオプションが有効な場合
     if args.commit {
         println!("\nCommitting with the generated message...");
         commit_with_message(&commit_message).await?;
-    } else {
+        return Ok(());
+    }
+
+    // 端末から実行された場合は、コミット・編集・再生成・中止を選ぶ
+    if args.no_review || !io::stdin().is_terminal() {
         println!(
             "\nTo use this message for commit, run: git commit -m \"{}\"",
             commit_message.replace("\"", "\\\"")
         );
+        return Ok(());
+    }
+
+    println!();
+    loop {
+        let action = review::select_action()?;
+        match action {
+            ReviewAction::Commit => {
+                commit_with_message(&commit_message).await?;
+                break;
+            }
+            ReviewAction::Edit => {
+                match review::edit_message(&commit_message) {
+                    Ok(edited) if edited.trim().is_empty() => {
+                        println!("The edited message is empty. Keeping the previous message.")
+                    }
+                    Ok(edited) => commit_message = edited,
+                    Err(e) => eprintln!("Error: {:#}\nKeeping the previous message.", e),
+                }
+                println!("\nCommit message:\n{}\n", commit_message);
+            }
+            ReviewAction::Regenerate | ReviewAction::RegenerateWithInstructions => {
+                let extra = if action == ReviewAction::RegenerateWithInstructions {
+                    let instructions = review::input_instructions()?;
+                    Some(review::revision_request(&commit_message, &instructions))
+                } else {
+                    None
+                };
+                // 再生成に失敗しても、それまでのメッセージで続けられるようにする
+                match generate_message(
+                    &changes,
+                    &config,
+                    &scopes,
+                    extra.as_deref(),
+                    stream,
+                    args.candidates,
+                )
+                .await
+                {
+                    Ok(message) => commit_message = message,
+                    Err(e) => eprintln!("Error: {:#}\nKeeping the previous message.", e),
+                }
+                println!();
+            }
+            ReviewAction::Abort => {
+                println!("Aborted. Nothing was committed.");
+                break;
+            }
+        }
     }
 
     Ok(())
@@ -0,0 +1,206 @@
+use anyhow::{Context, Result, anyhow};
+use promptuity::{
+    Promptuity, Term,
+    prompts::{Input, Select, SelectOption},
+    themes::FancyTheme,
+};
+use std::collections::hash_map::RandomState;
+use std::fs::{self, OpenOptions};
+use std::hash::{BuildHasher, Hasher};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process::Command;
+
+// 生成したメッセージに対する操作
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReviewAction {
+    Commit,
+    Edit,
+    Regenerate,
+    RegenerateWithInstructions,
+    Abort,
+}
+
+impl ReviewAction {
+    const ALL: [ReviewAction; 5] = [
+        ReviewAction::Commit,
+        ReviewAction::Edit,
+        ReviewAction::Regenerate,
+        ReviewAction::RegenerateWithInstructions,
+        ReviewAction::Abort,
+    ];
+
+    fn label(&self) -> &'static str {
+        match self {
+            ReviewAction::Commit => "Commit with this message",
+            ReviewAction::Edit => "Edit the message",
+            ReviewAction::Regenerate => "Regenerate",
+            ReviewAction::RegenerateWithInstructions => "Regenerate with instructions",
+            ReviewAction::Abort => "Abort",
+        }
+    }
+}
+
+pub fn select_action() -> Result<ReviewAction> {
+    let mut term = Term::default();
+    let mut theme = FancyTheme::default();
+    let mut p = Promptuity::new(&mut term, &mut theme);
+
+    let select_options: Vec<SelectOption<String>> = ReviewAction::ALL
+        .iter()
+        .map(|action| SelectOption::new(action.label(), action.label().to_string()))
+        .collect();
+
+    let mut select = Select::new("What do you want to do with this message?", select_options);
+
+    p.begin()?;
+    let selected = p.prompt(&mut select)?;
+    p.finish()?;
+
+    Ok(ReviewAction::ALL
+        .into_iter()
+        .find(|action| action.label() == selected)
+        .unwrap_or(ReviewAction::Abort))
+}
+
+// 再生成時にモデルへ伝える追加の指示を入力する
+pub fn input_instructions() -> Result<String> {
+    let mut term = Term::default();
+    let mut theme = FancyTheme::default();
+    let mut p = Promptuity::new(&mut term, &mut theme);
+
+    let mut input = Input::new("Instructions (e.g. shorter, mention the migration)");
+
+    p.begin()?;
+    let instructions = p.prompt(&mut input)?;
+    p.finish()?;
+
+    Ok(instructions.trim().to_string())
+}
+
+// 再生成のプロンプトに付け加える、前回のメッセージと指示
+pub fn revision_request(previous: &str, instructions: &str) -> String {
+    format!(
+        "\n\nA previous attempt produced this commit message:\n\n```\n{}\n```\n\nWrite a new commit message following these instructions: {}",
+        previous.trim(),
+        instructions
+    )
+}
+
+// $VISUAL または $EDITOR が設定されていればそのエディタで、なければ内蔵のエディタで編集する
+pub fn edit_message(message: &str) -> Result<String> {
+    let editor = ["VISUAL", "EDITOR"]
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|value| !value.trim().is_empty());
+
+    let edited = match editor {
+        Some(editor) => edit_with_external(&editor, message)?,
+        None => {
+            println!("Press Ctrl+S to save and Esc to exit without changes.");
+            // 保存せずに閉じた場合は元のメッセージのまま
+            crate::editor::Editor::with_content(message)
+                .run()?
+                .unwrap_or_else(|| message.to_string())
+        }
+    };
+    Ok(edited.trim_end().to_string())
+}
+
+pub fn edit_with_external(editor: &str, message: &str) -> Result<String> {
+    let path = create_message_file(message)?;
+
+    // エディタの指定には引数が含まれることがある（例: "code --wait"）
+    #[cfg(unix)]
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(format!("{} \"$1\"", editor))
+        .arg("aic")
+        .arg(&path)
+        .status();
+    #[cfg(not(unix))]
+    let status = Command::new("cmd")
+        .arg("/C")
+        .arg(format!("{} \"{}\"", editor, path.display()))
+        .status();
+
+    let result = match status {
+        Ok(status) if status.success() => {
+            fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))
+        }
+        Ok(status) => Err(anyhow!("The editor '{}' exited with {}", editor, status)),
+        Err(e) => Err(anyhow!("Failed to start the editor '{}': {}", editor, e)),
+    };
+    fs::remove_file(&path).ok();
+    result
+}
+
+// 編集用の一時ファイルを作る
+//   他のユーザーが同じ名前のファイルやシンボリックリンクを先に置けないよう、推測しにくい名前で新しく作り、所有者だけが読めるようにする
+pub fn create_message_file(message: &str) -> Result<PathBuf> {
+    let dir = std::env::temp_dir();
+    let mut last_error = None;
+
+    for _ in 0..16 {
+        let suffix = RandomState::new().build_hasher().finish();
+        let path = dir.join(format!(
+            "aic-message-{}-{:016x}.txt",
+            std::process::id(),
+            suffix
+        ));
+
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+
+        match options.open(&path) {
+            Ok(mut file) => {
+                let written = file.write_all(format!("{}\n", message).as_bytes());
+                if let Err(e) = written {
+                    fs::remove_file(&path).ok();
+                    return Err(e).with_context(|| format!("Failed to write {}", path.display()));
+                }
+                return Ok(path);
+            }
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => last_error = Some(e),
+            Err(e) => {
+                return Err(e).with_context(|| format!("Failed to create {}", path.display()));
+            }
+        }
+    }
+
+    Err(anyhow!(
+        "Failed to create a temporary file in {}: {}",
+        dir.display(),
+        last_error.map(|e| e.to_string()).unwrap_or_default()
+    ))
+}
+
+// 生成した候補から1つを選ぶ（表示は件名の行）
+pub fn select_candidate(candidates: &[String]) -> Result<String> {
+    let mut term = Term::default();
+    let mut theme = FancyTheme::default();
+    let mut p = Promptuity::new(&mut term, &mut theme);
+
+    let select_options: Vec<SelectOption<String>> = candidates
+        .iter()
+        .enumerate()
+        .map(|(index, candidate)| {
+            let subject = candidate.lines().next().unwrap_or_default();
+            SelectOption::new(format!("[{}] {}", index + 1, subject), index.to_string())
+        })
+        .collect();
+
+    let mut select = Select::new("Choose a commit message", select_options);
+
+    p.begin()?;
+    let selected = p.prompt(&mut select)?;
+    p.finish()?;
+
+    let index: usize = selected.parse().unwrap_or(0);
+    Ok(candidates[index.min(candidates.len() - 1)].clone())
+}
@@ -0,0 +1,261 @@
+use crate::config::SecretsConfig;
+use crate::diff;
+use crate::exclude::ExcludeList;
+use anyhow::{Context, Result};
+use regex::Regex;
+use std::fmt;
+use std::sync::OnceLock;
+
+// 既知の形式のキー（上から順に判定する）
+const PATTERNS: [(&str, &str); 9] = [
+    ("AWS access key", r"\b(?:AKIA|ASIA)[0-9A-Z]{16}\b"),
+    (
+        "AWS secret key",
+        r#"(?i)aws.{0,20}?(?:secret|private).{0,20}?['"=:\s]([A-Za-z0-9/+]{40})\b"#,
+    ),
+    (
+        "GitHub token",
+        r"\b(?:ghp|gho|ghu|ghs|ghr)_[A-Za-z0-9]{36,}\b",
+    ),
+    ("GitHub token", r"\bgithub_pat_[A-Za-z0-9_]{22,}\b"),
+    ("Slack token", r"\bxox[abposr]-[A-Za-z0-9-]{10,}"),
+    (
+        "Slack webhook",
+        r"https://hooks\.slack\.com/services/[A-Za-z0-9/_]+",
+    ),
+    ("Anthropic API key", r"\bsk-ant-[A-Za-z0-9_-]{20,}"),
+    (
+        "OpenAI API key",
+        r"\bsk-(?:proj-|svcacct-)?[A-Za-z0-9_-]{20,}",
+    ),
+    ("Private key", r"-----BEGIN[A-Z ]*PRIVATE KEY-----"),
+];
+
+// エントロピーを調べる候補の文字列
+const TOKEN_PATTERN: &str = r"[A-Za-z0-9+/_=-]{32,}";
+
+// ランダムな文字列とみなすエントロピー（1文字あたりのビット数）
+const ENTROPY_THRESHOLD: f64 = 4.5;
+
+fn patterns() -> &'static [(&'static str, Regex)] {
+    static PATTERNS_CELL: OnceLock<Vec<(&'static str, Regex)>> = OnceLock::new();
+    PATTERNS_CELL.get_or_init(|| {
+        PATTERNS
+            .iter()
+            .map(|(kind, pattern)| (*kind, Regex::new(pattern).unwrap()))
+            .collect()
+    })
+}
+
+fn token_pattern() -> &'static Regex {
+    static TOKEN: OnceLock<Regex> = OnceLock::new();
+    TOKEN.get_or_init(|| Regex::new(TOKEN_PATTERN).unwrap())
+}
+
+// 検出した秘密情報の場所
+#[derive(Debug, Clone, PartialEq)]
+pub struct Finding {
+    pub path: String,
+    pub line: usize,
+    pub kind: &'static str,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{} {}", self.path, self.line, self.kind)
+    }
+}
+
+pub struct Scanner {
+    allowlist: Vec<Regex>,
+    allow_paths: ExcludeList,
+}
+
+impl Scanner {
+    pub fn new(config: &SecretsConfig) -> Result<Self> {
+        let allowlist = config
+            .allowlist
+            .iter()
+            .map(|pattern| {
+                Regex::new(pattern)
+                    .with_context(|| format!("Invalid secrets allowlist pattern: {}", pattern))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            allowlist,
+            allow_paths: ExcludeList::new(&config.allow_paths)?,
+        })
+    }
+
+    // 差分の中の秘密情報を伏せ字にし、検出した場所を返す
+    pub fn redact(&self, diff: &str) -> (String, Vec<Finding>) {
+        let mut out = String::with_capacity(diff.len());
+        let mut findings = Vec::new();
+
+        let mut path = String::new();
+        let (mut old_line, mut new_line) = (0, 0);
+        let mut in_hunk = false;
+        let mut in_private_key = false;
+
+        for line in diff.split_inclusive('\n') {
+            if let Some(paths) = line.strip_prefix("diff --git ") {
+                path = diff::path_from_diff_line(paths.trim_end());
+                in_hunk = false;
+                in_private_key = false;
+                out.push_str(line);
+                continue;
+            }
+            if let Some(rest) = line.strip_prefix("@@") {
+                (old_line, new_line) = diff::hunk_starts(line);
+                in_hunk = true;
+
+                // "@@ ... @@" の後ろに続く関数名などの文脈も調べる
+                let end = rest.find("@@").map_or(line.len(), |i| i + 4);
+                let (range, context) = line.split_at(end);
+                out.push_str(range);
+                if self.allow_paths.is_excluded(&path) {
+                    out.push_str(context);
+                    continue;
+                }
+                let (redacted, kinds) = self.redact_line(context);
+                findings.extend(kinds.into_iter().map(|kind| Finding {
+                    path: path.clone(),
+                    line: new_line,
+                    kind,
+                }));
+                out.push_str(&redacted);
+                continue;
+            }
+
+            // ハンク内の行のみを調べる（ファイルヘッダーはそのまま）
+            let prefix = line.chars().next().filter(|c| matches!(c, ' ' | '+' | '-'));
+            let Some(prefix) = prefix.filter(|_| in_hunk) else {
+                out.push_str(line);
+                continue;
+            };
+
+            let number = if prefix == '-' { old_line } else { new_line };
+            match prefix {
+                '+' => new_line += 1,
+                '-' => old_line += 1,
+                _ => {
+                    old_line += 1;
+                    new_line += 1;
+                }
+            }
+
+            if self.allow_paths.is_excluded(&path) {
+                out.push_str(line);
+                continue;
+            }
+
+            let content = &line[1..];
+            if in_private_key {
+                if content.contains("PRIVATE KEY-----") && content.contains("-----END") {
+                    in_private_key = false;
+                }
+                out.push(prefix);
+                out.push_str("[REDACTED]");
+                out.push_str(line_ending(line));
+                continue;
+            }
+
+            let (redacted, kinds) = self.redact_line(content);
+            for kind in kinds {
+                if kind == "Private key" && !content.contains("-----END") {
+                    in_private_key = true;
+                }
+                findings.push(Finding {
+                    path: path.clone(),
+                    line: number,
+                    kind,
+                });
+            }
+            out.push(prefix);
+            out.push_str(&redacted);
+        }
+
+        (out, findings)
+    }
+
+    // 1行の中の秘密情報を伏せ字にする
+    fn redact_line(&self, content: &str) -> (String, Vec<&'static str>) {
+        let mut line = content.to_string();
+        let mut kinds = Vec::new();
+
+        for (kind, pattern) in patterns() {
+            line = pattern
+                .replace_all(&line, |caps: &regex::Captures| {
+                    // キャプチャがあればその部分だけを伏せる
+                    let whole = caps.get(0).unwrap();
+                    let secret = caps.get(1).unwrap_or(whole);
+                    if self.is_allowed(secret.as_str()) {
+                        return whole.as_str().to_string();
+                    }
+                    kinds.push(*kind);
+                    let start = secret.start() - whole.start();
+                    let end = secret.end() - whole.start();
+                    format!(
+                        "{}[REDACTED {}]{}",
+                        &whole.as_str()[..start],
+                        kind,
+                        &whole.as_str()[end..]
+                    )
+                })
+                .into_owned();
+        }
+
+        line = token_pattern()
+            .replace_all(&line, |caps: &regex::Captures| {
+                let token = &caps[0];
+                if looks_random(token) && !self.is_allowed(token) {
+                    kinds.push("High-entropy string");
+                    "[REDACTED]".to_string()
+                } else {
+                    token.to_string()
+                }
+            })
+            .into_owned();
+
+        (line, kinds)
+    }
+
+    fn is_allowed(&self, value: &str) -> bool {
+        self.allowlist.iter().any(|pattern| pattern.is_match(value))
+    }
+}
+
+fn line_ending(line: &str) -> &str {
+    if line.ends_with("\r\n") {
+        "\r\n"
+    } else if line.ends_with('\n') {
+        "\n"
+    } else {
+        ""
+    }
+}
+
+// 英字と数字が混在し、文字の出現が十分にばらついているか
+fn looks_random(token: &str) -> bool {
+    let has_digit = token.chars().any(|c| c.is_ascii_digit());
+    let has_upper = token.chars().any(|c| c.is_ascii_uppercase());
+    let has_lower = token.chars().any(|c| c.is_ascii_lowercase());
+    has_digit && has_upper && has_lower && shannon_entropy(token) >= ENTROPY_THRESHOLD
+}
+
+pub fn shannon_entropy(text: &str) -> f64 {
+    let mut counts = [0usize; 256];
+    for byte in text.bytes() {
+        counts[byte as usize] += 1;
+    }
+    let len = text.len() as f64;
+    counts
+        .iter()
+        .filter(|&&count| count > 0)
+        .map(|&count| {
+            let p = count as f64 / len;
+            -p * p.log2()
+        })
+        .sum()
+}
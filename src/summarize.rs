@@ -0,0 +1,67 @@
+use crate::api;
+use crate::config::Config;
+use crate::diff;
+use anyhow::{Context, Result};
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+// 差分の一部を要約させるためのシステムプロンプト
+const SYSTEM_PROMPT: &str = "You summarize one part of a large Git diff. The summaries of all parts will later be combined into a single commit message.
+List the changed files and describe what changed and, if it is apparent, why. Use a few concise bullet points.
+Do not write a commit message and do not repeat the code.";
+
+// 大きな差分を分割して並行に要約し、各部分の要約をまとめて返す
+pub async fn summarize_diff(
+    diff: &str,
+    config: &Config,
+    estimate: impl Fn(&str) -> usize,
+) -> Result<String> {
+    let chunks = diff::split_chunks(diff, config.diff.chunk_tokens, estimate);
+    let total = chunks.len();
+    let concurrency = config.diff.concurrency.max(1);
+
+    eprintln!(
+        "The staged diff exceeds the token budget. Summarizing it in {} part(s), {} at a time...",
+        total, concurrency
+    );
+
+    let config = Arc::new(config.clone());
+    let semaphore = Arc::new(Semaphore::new(concurrency));
+    let mut tasks = JoinSet::new();
+
+    for (index, chunk) in chunks.into_iter().enumerate() {
+        let config = Arc::clone(&config);
+        let semaphore = Arc::clone(&semaphore);
+        tasks.spawn(async move {
+            let _permit = semaphore.acquire_owned().await?;
+            let user_prompt = format!(
+                "Summarize the following part of a Git diff (part {} of {}):\n\n```\n{}\n```",
+                index + 1,
+                total,
+                chunk
+            );
+            let generation =
+                api::generate_commit_message(&config, SYSTEM_PROMPT, &user_prompt, None, None)
+                    .await
+                    .with_context(|| {
+                        format!("Failed to summarize part {} of the diff", index + 1)
+                    })?;
+            Ok::<_, anyhow::Error>((index, generation.message))
+        });
+    }
+
+    // 完了順に受け取り、元の順序に並べ直す
+    let mut summaries = vec![String::new(); total];
+    while let Some(result) = tasks.join_next().await {
+        let (index, summary) = result??;
+        summaries[index] = summary;
+    }
+
+    Ok(summaries
+        .iter()
+        .enumerate()
+        .map(|(index, summary)| format!("Part {}:\n{}", index + 1, summary.trim()))
+        .collect::<Vec<_>>()
+        .join("\n\n"))
+}